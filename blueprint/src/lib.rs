@@ -188,6 +188,16 @@ impl Data {
         }
     }
 
+    #[must_use]
+    pub const fn version(&self) -> u64 {
+        match self {
+            Self::Blueprint(data) => data.version,
+            Self::BlueprintBook(data) => data.version,
+            Self::UpgradePlanner(data) => data.version,
+            Self::DeconstructionPlanner(data) => data.version,
+        }
+    }
+
     #[must_use]
     pub const fn is_book(&self) -> bool {
         matches!(self, Self::BlueprintBook { .. })
@@ -198,6 +208,14 @@ impl Data {
         matches!(self, Self::Blueprint { .. })
     }
 
+    #[must_use]
+    pub const fn is_planner(&self) -> bool {
+        matches!(
+            self,
+            Self::UpgradePlanner { .. } | Self::DeconstructionPlanner { .. }
+        )
+    }
+
     #[must_use]
     pub const fn as_book(&self) -> Option<&Book> {
         match self {
@@ -240,6 +258,37 @@ impl Data {
         }
     }
 
+    /// Collects every blueprint contained in this data, including the ones in nested books.
+    ///
+    /// Each entry comes with the index path that leads to it, the path is empty for a plain blueprint.
+    #[must_use]
+    pub fn blueprints(&self) -> Vec<(Vec<u16>, &Self)> {
+        let mut result = Vec::new();
+        self.collect_blueprints(&mut Vec::new(), &mut result);
+        result
+    }
+
+    fn collect_blueprints<'a>(
+        &'a self,
+        path: &mut Vec<u16>,
+        result: &mut Vec<(Vec<u16>, &'a Self)>,
+    ) {
+        match self {
+            Self::Blueprint(_) => result.push((path.clone(), self)),
+            Self::BlueprintBook(book) => {
+                let mut entries = book.blueprints.iter().collect::<Vec<_>>();
+                entries.sort_by_key(|entry| entry.index);
+
+                for entry in entries {
+                    path.push(entry.index);
+                    entry.data.collect_blueprints(path, result);
+                    path.pop();
+                }
+            }
+            _ => {}
+        }
+    }
+
     fn normalize_positions(&mut self) {
         match self {
             Self::BlueprintBook(data) => {
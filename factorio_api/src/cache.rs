@@ -0,0 +1,293 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Client, FactorioApiError, PortalLongEntry};
+
+/// Makes the names of temporary files unique when multiple threads store the same entry.
+static TMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// The mod portal only allows alphanumeric characters, dashes & underscores in mod names,
+/// some old mods also contain spaces.
+///
+/// Names are used as file names in the cache, so anything else is rejected.
+fn check_mod_name(mod_name: &str) -> Result<(), FactorioApiError> {
+    let valid = !mod_name.is_empty()
+        && mod_name.len() <= 100
+        && mod_name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | ' '));
+
+    if valid {
+        Ok(())
+    } else {
+        Err(FactorioApiError::InvalidModName(mod_name.to_owned()))
+    }
+}
+
+/// A cached portal response together with the info needed to revalidate it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedEntry {
+    pub etag: Option<String>,
+
+    /// Unix timestamp (seconds) of the last time the entry was fetched or revalidated.
+    pub fetched_at: u64,
+
+    pub entry: PortalLongEntry,
+}
+
+impl CachedEntry {
+    fn age(&self) -> Duration {
+        Duration::from_secs(unix_now().saturating_sub(self.fetched_at))
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// On-disk cache of [`full_info`](Client::full_info) responses, one `<mod name>.json` per mod.
+///
+/// Entries older than `max_age` are revalidated with their `ETag`. If the portal can't be reached
+/// or answers with a server error the stale entry is used instead. In offline mode the portal is never contacted.
+#[derive(Debug, Clone)]
+pub struct PortalCache {
+    dir: PathBuf,
+    max_age: Duration,
+    offline: bool,
+}
+
+impl PortalCache {
+    #[must_use]
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            max_age: Duration::from_secs(24 * 60 * 60),
+            offline: false,
+        }
+    }
+
+    #[must_use]
+    pub const fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    #[must_use]
+    pub const fn offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    #[must_use]
+    pub const fn is_offline(&self) -> bool {
+        self.offline
+    }
+
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path(&self, mod_name: &str) -> Result<PathBuf, FactorioApiError> {
+        check_mod_name(mod_name)?;
+        Ok(self.dir.join(format!("{mod_name}.json")))
+    }
+
+    /// Reads the cached entry of a mod regardless of its age.
+    #[must_use]
+    pub fn get(&self, mod_name: &str) -> Option<CachedEntry> {
+        let bytes = fs::read(self.path(mod_name).ok()?).ok()?;
+        serde_json::from_slice(&bytes).ok()
+    }
+
+    /// Stores an entry, the file is replaced atomically so concurrent readers never see a partial entry.
+    pub fn insert(&self, mod_name: &str, entry: &CachedEntry) -> Result<(), FactorioApiError> {
+        let path = self.path(mod_name)?;
+        fs::create_dir_all(&self.dir)?;
+
+        let tmp = path.with_extension(format!(
+            "{}-{}.tmp",
+            std::process::id(),
+            TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::write(&tmp, serde_json::to_vec(entry)?)?;
+        if let Err(err) = fs::rename(&tmp, &path) {
+            fs::remove_file(&tmp).ok();
+            return Err(err.into());
+        }
+
+        Ok(())
+    }
+
+    /// Full info of a mod from the cache, fetched or revalidated through `client` when needed.
+    pub async fn full_info(
+        &self,
+        client: &Client,
+        mod_name: &str,
+    ) -> Result<PortalLongEntry, FactorioApiError> {
+        check_mod_name(mod_name)?;
+        let cached = self.get(mod_name);
+
+        if self.offline {
+            return cached
+                .map(|cached| cached.entry)
+                .ok_or_else(|| FactorioApiError::NotCached(mod_name.to_owned()));
+        }
+
+        if let Some(cached) = &cached {
+            if cached.age() < self.max_age {
+                return Ok(cached.entry.clone());
+            }
+        }
+
+        let etag = cached.as_ref().and_then(|cached| cached.etag.as_deref());
+        let res = match client.full_info_if_modified(mod_name, etag).await {
+            Ok(res) => res,
+            // a broken response body is as good as no response when there is a cached entry
+            Err(err) if err.is_transient() || matches!(err, FactorioApiError::Json(_)) => {
+                return cached.map(|cached| cached.entry).ok_or(err);
+            }
+            Err(err) => return Err(err),
+        };
+
+        let updated = match (res, cached) {
+            (Some((entry, etag)), _) => CachedEntry {
+                etag,
+                fetched_at: unix_now(),
+                entry,
+            },
+            (None, Some(cached)) => CachedEntry {
+                fetched_at: unix_now(),
+                ..cached
+            },
+            (None, None) => {
+                return Err(FactorioApiError::ApiError(format!(
+                    "{mod_name} was not modified but is not cached"
+                )))
+            }
+        };
+
+        // a failed cache write only costs a request next time
+        self.insert(mod_name, &updated).ok();
+
+        Ok(updated.entry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    fn entry() -> PortalLongEntry {
+        serde_json::from_value(serde_json::json!({
+            "downloads_count": 1,
+            "name": "test",
+            "owner": "me",
+            "releases": [{
+                "download_url": "/download/test/1",
+                "file_name": "test_1.0.0.zip",
+                "info_json": {
+                    "factorio_version": "1.1",
+                    "dependencies": ["base >= 1.1.0", "? other"]
+                },
+                "released_at": "2024-01-01T00:00:00.000000Z",
+                "version": "1.0.0",
+                "sha1": "0000"
+            }],
+            "summary": "",
+            "title": "Test",
+            "created_at": "2024-01-01T00:00:00.000000Z",
+            "homepage": "",
+            "license": {
+                "description": "",
+                "id": "default_mit",
+                "name": "MIT",
+                "title": "MIT",
+                "url": ""
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn offline_uses_only_cached_entries() {
+        let dir = std::env::temp_dir().join(format!("portal-cache-test-{}", std::process::id()));
+        let cache = PortalCache::new(&dir).offline(true);
+        let client = Client::new(crate::ClientConfig::new().endpoint("http://127.0.0.1:9"));
+
+        let missing = tokio_test::block_on(cache.full_info(&client, "test"));
+        assert!(matches!(missing, Err(FactorioApiError::NotCached(_))));
+
+        let escaping = tokio_test::block_on(cache.full_info(&client, "../test"));
+        assert!(matches!(escaping, Err(FactorioApiError::InvalidModName(_))));
+
+        cache
+            .insert(
+                "test",
+                &CachedEntry {
+                    etag: Some("\"abc\"".to_owned()),
+                    fetched_at: 0,
+                    entry: entry(),
+                },
+            )
+            .unwrap();
+
+        let cached = tokio_test::block_on(cache.full_info(&client, "test")).unwrap();
+        assert_eq!(cached.name, "test");
+        assert_eq!(cached.releases[0].info_json.dependencies.len(), 2);
+        assert_eq!(
+            cached.releases[0].info_json.dependencies[0].version(),
+            &mod_util::mod_info::DependencyVersion::HigherOrEqual(
+                mod_util::mod_info::Version::new(1, 1, 0)
+            )
+        );
+
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn server_errors_fall_back_to_stale_entries() {
+        let dir =
+            std::env::temp_dir().join(format!("portal-cache-stale-test-{}", std::process::id()));
+        let cache = PortalCache::new(&dir);
+
+        let (endpoint, requests) =
+            crate::test_server::stand_in_server(vec![crate::test_server::response(
+                "502 Bad Gateway",
+                "content-type: text/html\r\n",
+                b"<html>bad gateway</html>",
+            )]);
+        let client = Client::new(
+            crate::ClientConfig::new()
+                .endpoint(endpoint)
+                .min_interval(Duration::ZERO),
+        );
+
+        cache
+            .insert(
+                "test",
+                &CachedEntry {
+                    etag: Some("\"abc\"".to_owned()),
+                    fetched_at: 0,
+                    entry: entry(),
+                },
+            )
+            .unwrap();
+
+        let stale = tokio_test::block_on(cache.full_info(&client, "test")).unwrap();
+        assert_eq!(stale.name, "test");
+        assert!(requests.recv().unwrap().contains("if-none-match: \"abc\""));
+
+        fs::remove_dir_all(dir).ok();
+    }
+}
@@ -0,0 +1,816 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, OnceLock, PoisonError},
+    time::{Duration, Instant},
+};
+
+use futures_util::StreamExt;
+use mod_util::mod_info::Version;
+use reqwest::{
+    header::{CONTENT_RANGE, ETAG, IF_NONE_MATCH, RANGE, RETRY_AFTER},
+    RequestBuilder, Response, StatusCode,
+};
+use serde::de::DeserializeOwned;
+use tokio::{
+    io::AsyncWriteExt,
+    sync::{Semaphore, SemaphorePermit},
+};
+
+use crate::{
+    AuthDetails, FactorioApiError, ModRelease, PortalListParams, PortalListResponse,
+    PortalLongEntry, PortalResponse, PortalShortEntry, DEFAULT_ENDPOINT,
+};
+
+/// Longest time to wait before retrying a rate limited request.
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Downloaded bytes between two progress reports.
+const PROGRESS_STEP: u64 = 256 * 1024;
+
+/// Time between two attempts to take the lock of a download that another process is running.
+const LOCK_POLL: Duration = Duration::from_millis(500);
+
+/// A mod release that should be downloaded to `path`.
+#[derive(Debug, Clone)]
+pub struct ModDownload {
+    pub name: String,
+    pub version: Version,
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DownloadProgress {
+    pub downloaded: u64,
+
+    /// Size of the whole file if the portal sent it.
+    pub total: Option<u64>,
+}
+
+/// Unfinished downloads are kept next to their destination so they can be resumed.
+fn part_path(path: &Path) -> PathBuf {
+    let mut part = path.as_os_str().to_owned();
+    part.push(".part");
+    PathBuf::from(part)
+}
+
+/// Downloads of the same file from multiple processes take turns through a lock file next to the destination.
+fn lock_path(path: &Path) -> PathBuf {
+    let mut lock = path.as_os_str().to_owned();
+    lock.push(".lock");
+    PathBuf::from(lock)
+}
+
+/// Waits until no other process downloads to `path`, the lock is held until the returned file is dropped.
+async fn lock_download(path: &Path) -> Result<std::fs::File, FactorioApiError> {
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(false)
+        .open(lock_path(path))?;
+
+    loop {
+        match fs2::FileExt::try_lock_exclusive(&file) {
+            Ok(()) => return Ok(file),
+            Err(err) if err.kind() == fs2::lock_contended_error().kind() => {
+                tokio::time::sleep(LOCK_POLL).await;
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
+
+/// First byte of a partial response, parsed from `Content-Range: bytes <start>-<end>/<size>`.
+fn content_range_start(res: &Response) -> Option<u64> {
+    let range = res.headers().get(CONTENT_RANGE)?.to_str().ok()?;
+    range
+        .strip_prefix("bytes ")?
+        .split_once('-')?
+        .0
+        .parse()
+        .ok()
+}
+
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    /// Base URL of the mod portal, defaults to `FACTORIO_API_ENDPOINT` or the official portal.
+    pub endpoint: Option<String>,
+
+    /// Maximum number of requests in flight at the same time.
+    pub max_concurrent: usize,
+
+    /// Minimum time between the start of two requests.
+    pub min_interval: Duration,
+
+    /// How often a request is retried after the portal answered with HTTP 429.
+    pub max_retries: u32,
+
+    /// Initial wait before retrying a rate limited request without `Retry-After` header, doubles with every retry.
+    pub backoff: Duration,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: None,
+            max_concurrent: 4,
+            min_interval: Duration::from_millis(100),
+            max_retries: 5,
+            backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+impl ClientConfig {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = Some(endpoint.into());
+        self
+    }
+
+    #[must_use]
+    pub const fn max_concurrent(mut self, max_concurrent: usize) -> Self {
+        self.max_concurrent = max_concurrent;
+        self
+    }
+
+    #[must_use]
+    pub const fn min_interval(mut self, min_interval: Duration) -> Self {
+        self.min_interval = min_interval;
+        self
+    }
+
+    #[must_use]
+    pub const fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    #[must_use]
+    pub const fn backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+}
+
+#[derive(Debug)]
+struct Limits {
+    concurrent: Semaphore,
+    next_request: Mutex<Instant>,
+    min_interval: Duration,
+    max_retries: u32,
+    backoff: Duration,
+}
+
+/// Mod portal client that reuses its connections and limits how fast requests are sent.
+///
+/// Clones share the connection pool and the limits.
+/// Pooled connections belong to the tokio runtime they were opened on, so a client should only be used from one runtime.
+#[derive(Debug, Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    endpoint: String,
+    limits: Arc<Limits>,
+}
+
+impl Default for Client {
+    fn default() -> Self {
+        Self::new(ClientConfig::default())
+    }
+}
+
+impl Client {
+    #[must_use]
+    pub fn new(config: ClientConfig) -> Self {
+        let endpoint = config
+            .endpoint
+            .or_else(|| std::env::var("FACTORIO_API_ENDPOINT").ok())
+            .unwrap_or_else(|| DEFAULT_ENDPOINT.to_owned());
+
+        Self {
+            http: reqwest::Client::new(),
+            endpoint: endpoint.trim_end_matches('/').to_owned(),
+            limits: Arc::new(Limits {
+                concurrent: Semaphore::new(config.max_concurrent.max(1)),
+                next_request: Mutex::new(Instant::now()),
+                min_interval: config.min_interval,
+                max_retries: config.max_retries,
+                backoff: config.backoff,
+            }),
+        }
+    }
+
+    /// Client with the default config that is used by the async free functions of this crate.
+    ///
+    /// Its pooled connections belong to the runtime they were opened on,
+    /// so the free functions should only be called from one runtime. Use your own [`Client`] per runtime otherwise.
+    #[must_use]
+    pub fn shared() -> Self {
+        static SHARED: OnceLock<Client> = OnceLock::new();
+        SHARED.get_or_init(Self::default).clone()
+    }
+
+    #[must_use]
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// Waits until the next request may be started according to `min_interval`.
+    async fn wait_for_slot(&self) {
+        let wait = {
+            let mut next = self
+                .limits
+                .next_request
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+
+            let now = Instant::now();
+            let slot = (*next).max(now);
+            *next = slot + self.limits.min_interval;
+            drop(next);
+
+            slot - now
+        };
+
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    fn retry_delay(&self, res: &Response, retry: u32) -> Duration {
+        res.headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse().ok())
+            .map_or_else(
+                || self.limits.backoff.saturating_mul(1 << retry.min(16)),
+                Duration::from_secs,
+            )
+            .min(MAX_BACKOFF)
+    }
+
+    /// Sends a request once the limits allow it.
+    ///
+    /// The permit counts towards `max_concurrent` and has to be kept until the body was read.
+    #[allow(clippy::significant_drop_tightening)] // the permit is returned with the response
+    async fn send(
+        &self,
+        request: RequestBuilder,
+    ) -> Result<(Response, SemaphorePermit<'_>), FactorioApiError> {
+        let permit = self
+            .limits
+            .concurrent
+            .acquire()
+            .await
+            .map_err(|_| FactorioApiError::ApiError("client was shut down".to_owned()))?;
+
+        let mut retry = 0;
+        loop {
+            let Some(attempt) = request.try_clone() else {
+                return Err(FactorioApiError::ApiError(
+                    "request can not be sent again".to_owned(),
+                ));
+            };
+
+            self.wait_for_slot().await;
+            let res = attempt.send().await?;
+
+            if res.status() != StatusCode::TOO_MANY_REQUESTS {
+                return Ok((res, permit));
+            }
+
+            if retry >= self.limits.max_retries {
+                return Err(FactorioApiError::RateLimited);
+            }
+
+            tokio::time::sleep(self.retry_delay(&res, retry)).await;
+            retry += 1;
+        }
+    }
+
+    async fn get_portal<T: DeserializeOwned>(&self, path: &str) -> Result<T, FactorioApiError> {
+        let (res, _permit) = self
+            .send(self.http.get(format!("{}{path}", self.endpoint)))
+            .await?;
+
+        match serde_json::from_slice(&res.bytes().await?)? {
+            PortalResponse::Ok(res) => Ok(res),
+            PortalResponse::Err { message } => Err(FactorioApiError::ApiError(message)),
+        }
+    }
+
+    pub async fn auth(
+        &self,
+        username: &str,
+        password: &str,
+    ) -> Result<AuthDetails, FactorioApiError> {
+        let body: HashMap<&str, &str> = [
+            ("username", username),
+            ("password", password),
+            ("api_version", "4"),
+        ]
+        .iter()
+        .copied()
+        .collect();
+
+        let (res, _permit) = self
+            .send(
+                self.http
+                    .post("https://auth.factorio.com/api-login")
+                    .form(&body),
+            )
+            .await?;
+
+        Ok(serde_json::from_slice(&res.bytes().await?)?)
+    }
+
+    pub async fn portal_list(
+        &self,
+        params: PortalListParams,
+    ) -> Result<PortalListResponse, FactorioApiError> {
+        self.get_portal(&format!("/api/mods?{}", params.build()))
+            .await
+    }
+
+    pub async fn short_info(&self, mod_name: &str) -> Result<PortalShortEntry, FactorioApiError> {
+        self.get_portal(&format!("/api/mods/{mod_name}")).await
+    }
+
+    pub async fn full_info(&self, mod_name: &str) -> Result<PortalLongEntry, FactorioApiError> {
+        self.get_portal(&format!("/api/mods/{mod_name}/full")).await
+    }
+
+    /// Like [`full_info`](Self::full_info) but returns `None` if the entry still matches `etag`.
+    ///
+    /// The `ETag` of the returned entry is passed along for later revalidation.
+    pub async fn full_info_if_modified(
+        &self,
+        mod_name: &str,
+        etag: Option<&str>,
+    ) -> Result<Option<(PortalLongEntry, Option<String>)>, FactorioApiError> {
+        let mut request = self
+            .http
+            .get(format!("{}/api/mods/{mod_name}/full", self.endpoint));
+        if let Some(etag) = etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+
+        let (res, _permit) = self.send(request).await?;
+        if res.status() == StatusCode::NOT_MODIFIED {
+            return Ok(None);
+        }
+
+        // error pages of the portal are not JSON, so report them as the status they came with
+        if res.status().is_server_error() {
+            res.error_for_status_ref()?;
+        }
+
+        let etag = res
+            .headers()
+            .get(ETAG)
+            .and_then(|value| value.to_str().ok())
+            .map(ToOwned::to_owned);
+
+        match serde_json::from_slice(&res.bytes().await?)? {
+            PortalResponse::Ok(entry) => Ok(Some((entry, etag))),
+            PortalResponse::Err { message } => Err(FactorioApiError::ApiError(message)),
+        }
+    }
+
+    pub async fn fetch_mod_raw(
+        &self,
+        download_url: &str,
+        username: &str,
+        token: &str,
+    ) -> Result<Vec<u8>, FactorioApiError> {
+        let (res, _permit) = self
+            .send(self.http.get(format!(
+                "{}{download_url}?username={username}&token={token}",
+                self.endpoint
+            )))
+            .await?;
+
+        Ok(res.error_for_status()?.bytes().await?.to_vec())
+    }
+
+    /// Downloads a release of a mod and verifies it with [`crate::verify_release`].
+    pub async fn fetch_mod(
+        &self,
+        mod_name: &str,
+        version: &Version,
+        username: &str,
+        token: &str,
+    ) -> Result<Vec<u8>, FactorioApiError> {
+        let mod_info = self.short_info(mod_name).await?;
+
+        for release in mod_info.releases {
+            if release.version != *version {
+                continue;
+            }
+
+            let data = self
+                .fetch_mod_raw(&release.download_url, username, token)
+                .await?;
+            crate::verify_release(&release, &data)?;
+            return Ok(data);
+        }
+
+        Err(FactorioApiError::NoRelease(mod_name.to_owned()))
+    }
+
+    /// Downloads a release to `path`, a previously interrupted download is resumed with a range request.
+    ///
+    /// The data is verified with [`crate::verify_release`] and only moved to `path` when it's complete and valid.
+    /// Other processes downloading to the same `path` are waited for, if one of them finished the release is not downloaded again.
+    pub async fn download_release(
+        &self,
+        release: &ModRelease,
+        username: &str,
+        token: &str,
+        path: &Path,
+        progress: impl Fn(DownloadProgress),
+    ) -> Result<(), FactorioApiError> {
+        let lock = lock_download(path).await?;
+        if let Ok(data) = tokio::fs::read(path).await {
+            if crate::verify_release(release, &data).is_ok() {
+                return Ok(());
+            }
+        }
+
+        let part = part_path(path);
+        let mut offset = tokio::fs::metadata(&part).await.map_or(0, |m| m.len());
+
+        let (mut res, _permit) = loop {
+            let mut request = self.http.get(format!(
+                "{}{}?username={username}&token={token}",
+                self.endpoint, release.download_url
+            ));
+            if offset > 0 {
+                request = request.header(RANGE, format!("bytes={offset}-"));
+            }
+
+            let (res, permit) = self.send(request).await?;
+
+            // the partial file is already complete or belongs to something else
+            if res.status() == StatusCode::RANGE_NOT_SATISFIABLE && offset > 0 {
+                tokio::fs::remove_file(&part).await?;
+                offset = 0;
+                continue;
+            }
+
+            let res = res.error_for_status()?;
+
+            // appending a range that doesn't start at the end of the partial file would corrupt it
+            if res.status() == StatusCode::PARTIAL_CONTENT
+                && offset > 0
+                && content_range_start(&res) != Some(offset)
+            {
+                tokio::fs::remove_file(&part).await.ok();
+                offset = 0;
+                continue;
+            }
+
+            break (res, permit);
+        };
+
+        let resumed = res.status() == StatusCode::PARTIAL_CONTENT;
+        if !resumed {
+            offset = 0;
+        }
+
+        let total = res.content_length().map(|len| len + offset);
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(resumed)
+            .truncate(!resumed)
+            .open(&part)
+            .await?;
+
+        let mut downloaded = offset;
+        let mut reported = offset;
+        progress(DownloadProgress { downloaded, total });
+
+        while let Some(chunk) = res.chunk().await? {
+            file.write_all(&chunk).await?;
+            downloaded += chunk.len() as u64;
+
+            if downloaded - reported >= PROGRESS_STEP {
+                progress(DownloadProgress { downloaded, total });
+                reported = downloaded;
+            }
+        }
+
+        file.sync_all().await?;
+        drop(file);
+
+        if downloaded != reported {
+            progress(DownloadProgress { downloaded, total });
+        }
+
+        let data = tokio::fs::read(&part).await?;
+        if let Err(err) = crate::verify_release(release, &data) {
+            // resuming a broken download would not fix it
+            tokio::fs::remove_file(&part).await.ok();
+            return Err(err);
+        }
+
+        tokio::fs::rename(&part, path).await?;
+
+        // removed while still locked, whoever waited for the lock finds the finished file
+        tokio::fs::remove_file(lock_path(path)).await.ok();
+        drop(lock);
+
+        Ok(())
+    }
+
+    /// Looks up the release of a mod and downloads it, see [`download_release`](Self::download_release).
+    pub async fn download_mod(
+        &self,
+        download: &ModDownload,
+        username: &str,
+        token: &str,
+        progress: impl Fn(DownloadProgress),
+    ) -> Result<(), FactorioApiError> {
+        let mod_info = self.short_info(&download.name).await?;
+        let release = mod_info
+            .releases
+            .iter()
+            .find(|release| release.version == download.version)
+            .ok_or_else(|| FactorioApiError::NoRelease(download.name.clone()))?;
+
+        self.download_release(release, username, token, &download.path, progress)
+            .await
+    }
+
+    /// Downloads multiple mods with up to `workers` downloads at the same time.
+    ///
+    /// Transient failures are retried (resuming the download) until `attempts` tries were made.
+    /// A download that doesn't match its release is downloaded again from scratch once.
+    /// The results are in the same order as `downloads`.
+    // all downloads run on the calling task, so `progress` doesn't have to be `Sync`
+    #[allow(clippy::future_not_send)]
+    pub async fn download_mods<F: Fn(&ModDownload, DownloadProgress)>(
+        &self,
+        downloads: &[ModDownload],
+        (username, token): (&str, &str),
+        workers: usize,
+        attempts: u32,
+        progress: F,
+    ) -> Vec<Result<(), FactorioApiError>> {
+        let progress = &progress;
+
+        futures_util::stream::iter(downloads)
+            .map(|download| async move {
+                let mut attempt = 1;
+                let mut redownloaded = false;
+                loop {
+                    let res = self
+                        .download_mod(download, username, token, |p| progress(download, p))
+                        .await;
+
+                    match res {
+                        Err(err) if err.is_transient() && attempt < attempts => attempt += 1,
+                        // the broken file was removed, so this starts from scratch
+                        Err(
+                            FactorioApiError::InvalidZip(_) | FactorioApiError::HashMismatch { .. },
+                        ) if !redownloaded => redownloaded = true,
+                        res => return res,
+                    }
+                }
+            })
+            .buffered(workers.max(1))
+            .collect()
+            .await
+    }
+
+    pub async fn fetch_mod_with_password(
+        &self,
+        mod_name: &str,
+        version: &Version,
+        username: &str,
+        password: &str,
+    ) -> Result<Vec<u8>, FactorioApiError> {
+        let auth_res = self.auth(username, password).await?;
+        self.fetch_mod(mod_name, version, &auth_res.username, &auth_res.token)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use std::io::{Cursor, Write};
+
+    use sha1::Digest;
+
+    use super::*;
+    use crate::test_server::{response, stand_in_server};
+
+    #[test]
+    fn retries_rate_limited_requests() {
+        let body = br#"{"downloads_count": 1, "name": "test", "owner": "me", "releases": [], "summary": "", "title": "Test"}"#;
+        let (endpoint, _) = stand_in_server(vec![
+            response("429 Too Many Requests", "retry-after: 0\r\n", b""),
+            response("200 OK", "", body),
+        ]);
+
+        let client = Client::new(
+            ClientConfig::new()
+                .endpoint(endpoint)
+                .min_interval(Duration::ZERO),
+        );
+
+        let info = tokio_test::block_on(client.short_info("test")).unwrap();
+        assert_eq!(info.name, "test");
+    }
+
+    #[test]
+    fn gives_up_when_rate_limited() {
+        let limited = response("429 Too Many Requests", "", b"");
+        let (endpoint, _) = stand_in_server(vec![limited.clone(), limited]);
+
+        let client = Client::new(
+            ClientConfig::new()
+                .endpoint(endpoint)
+                .min_interval(Duration::ZERO)
+                .backoff(Duration::ZERO)
+                .max_retries(1),
+        );
+
+        let res = tokio_test::block_on(client.short_info("test"));
+        assert!(matches!(res, Err(FactorioApiError::RateLimited)));
+    }
+
+    /// A small mod zip and the release that describes it.
+    fn test_release() -> (Vec<u8>, ModRelease) {
+        let (data, release) = test_release_json();
+        (data, serde_json::from_value(release).unwrap())
+    }
+
+    fn test_release_json() -> (Vec<u8>, serde_json::Value) {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        writer
+            .start_file("test_1.0.0/info.json", zip::write::FileOptions::default())
+            .unwrap();
+        writer.write_all(&[b'x'; 4096]).unwrap();
+        let data = writer.finish().unwrap().into_inner();
+
+        let release = serde_json::json!({
+            "download_url": "/download/test/1",
+            "file_name": "test_1.0.0.zip",
+            "info_json": { "factorio_version": "1.1" },
+            "released_at": "2024-01-01T00:00:00.000000Z",
+            "version": "1.0.0",
+            "sha1": format!("{:x}", sha1::Sha1::digest(&data)),
+        });
+
+        (data, release)
+    }
+
+    #[test]
+    fn resumes_partial_download() {
+        let (data, release) = test_release();
+
+        let dir = std::env::temp_dir().join(format!("download-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("test_1.0.0.zip");
+
+        let half = data.len() / 2;
+        std::fs::write(part_path(&path), &data[..half]).unwrap();
+
+        let (endpoint, requests) = stand_in_server(vec![response(
+            "206 Partial Content",
+            &format!(
+                "content-range: bytes {half}-{}/{}\r\n",
+                data.len() - 1,
+                data.len()
+            ),
+            &data[half..],
+        )]);
+        let client = Client::new(ClientConfig::new().endpoint(endpoint));
+
+        let reports = std::cell::RefCell::new(Vec::new());
+        tokio_test::block_on(
+            client.download_release(&release, "user", "token", &path, |p| {
+                reports.borrow_mut().push(p);
+            }),
+        )
+        .unwrap();
+
+        assert!(requests
+            .recv()
+            .unwrap()
+            .contains(&format!("range: bytes={half}-")));
+        assert_eq!(std::fs::read(&path).unwrap(), data);
+        assert!(!part_path(&path).exists());
+        assert!(!lock_path(&path).exists());
+        assert_eq!(
+            reports.borrow().last(),
+            Some(&DownloadProgress {
+                downloaded: data.len() as u64,
+                total: Some(data.len() as u64),
+            })
+        );
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn restarts_download_when_range_does_not_match() {
+        let (data, release) = test_release();
+
+        let dir = std::env::temp_dir().join(format!("download-range-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("test_1.0.0.zip");
+
+        let half = data.len() / 2;
+        std::fs::write(part_path(&path), &data[..half]).unwrap();
+
+        // the server ignores where the partial file ends and sends the range from the start
+        let (endpoint, requests) = stand_in_server(vec![
+            response(
+                "206 Partial Content",
+                &format!("content-range: bytes 0-{}/{}\r\n", half - 1, data.len()),
+                &data[..half],
+            ),
+            response("200 OK", "", &data),
+        ]);
+        let client = Client::new(
+            ClientConfig::new()
+                .endpoint(endpoint)
+                .min_interval(Duration::ZERO),
+        );
+
+        tokio_test::block_on(client.download_release(&release, "user", "token", &path, |_| {}))
+            .unwrap();
+
+        assert!(requests.recv().unwrap().contains("range: bytes="));
+        assert!(!requests.recv().unwrap().contains("range: bytes="));
+        assert_eq!(std::fs::read(&path).unwrap(), data);
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn downloads_mismatching_files_again_once() {
+        let (data, release) = test_release_json();
+        let info = serde_json::to_vec(&serde_json::json!({
+            "downloads_count": 1,
+            "name": "test",
+            "owner": "me",
+            "releases": [release],
+            "summary": "",
+            "title": "Test",
+        }))
+        .unwrap();
+
+        let dir = std::env::temp_dir().join(format!("download-again-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let download = ModDownload {
+            name: "test".to_owned(),
+            version: Version::new(1, 0, 0),
+            path: dir.join("test_1.0.0.zip"),
+        };
+
+        let mut broken = data;
+        broken[0] ^= 0xff;
+        let (endpoint, requests) = stand_in_server(vec![
+            response("200 OK", "", &info),
+            response("200 OK", "", &broken),
+            response("200 OK", "", &info),
+            response("200 OK", "", &broken),
+        ]);
+        let client = Client::new(
+            ClientConfig::new()
+                .endpoint(endpoint)
+                .min_interval(Duration::ZERO),
+        );
+
+        let results = tokio_test::block_on(client.download_mods(
+            std::slice::from_ref(&download),
+            ("user", "token"),
+            1,
+            5,
+            |_, _| {},
+        ));
+
+        // a second mismatch is not retried although there are attempts left
+        assert!(matches!(
+            results[..],
+            [Err(
+                FactorioApiError::InvalidZip(_) | FactorioApiError::HashMismatch { .. }
+            )]
+        ));
+        assert_eq!(requests.iter().count(), 4);
+        assert!(!download.path.exists());
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
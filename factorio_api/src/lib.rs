@@ -1,6 +1,9 @@
 #![allow(clippy::module_name_repetitions)]
 
+use std::io::Cursor;
+
 use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
 
 use mod_util::mod_info::Version;
 
@@ -22,8 +25,68 @@ pub enum FactorioApiError {
 
     #[error("factorio api error: {0}")]
     ApiError(String),
+
+    #[error("downloaded mod is not a valid zip: {0}")]
+    InvalidZip(#[from] zip::result::ZipError),
+
+    #[error("no cached mod portal info for {0} while offline")]
+    NotCached(String),
+
+    #[error("invalid mod name: {0:?}")]
+    InvalidModName(String),
+
+    #[error("rate limited by the mod portal")]
+    RateLimited,
+
+    #[error("downloaded mod does not match its sha1: expected {expected}, got {actual}")]
+    HashMismatch { expected: String, actual: String },
 }
 
+impl FactorioApiError {
+    /// Whether trying the same request again might succeed.
+    ///
+    /// Only connection problems, timeouts, server errors & rate limits are transient,
+    /// other error responses of the portal won't change when asking again.
+    #[must_use]
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::Reqwest(err) => {
+                err.is_connect()
+                    || err.is_timeout()
+                    || err.status().is_some_and(|status| {
+                        status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+                    })
+            }
+            Self::RateLimited => true,
+            _ => false,
+        }
+    }
+}
+
+/// Checks that downloaded data is a readable zip and matches the sha1 of the release.
+pub fn verify_release(release: &ModRelease, data: &[u8]) -> Result<(), FactorioApiError> {
+    zip::ZipArchive::new(Cursor::new(data))?;
+
+    let actual = format!("{:x}", Sha1::digest(data));
+    if !actual.eq_ignore_ascii_case(&release.sha1) {
+        return Err(FactorioApiError::HashMismatch {
+            expected: release.sha1.clone(),
+            actual,
+        });
+    }
+
+    Ok(())
+}
+
+pub use cache::*;
+mod cache;
+
+pub use client::*;
+mod client;
+
+#[cfg(test)]
+mod test_server;
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(untagged)]
 enum PortalResponse<T> {
@@ -31,129 +94,93 @@ enum PortalResponse<T> {
     Err { message: String },
 }
 
+/// Blocking wrappers around a [`Client`] with the default config.
+///
+/// The requests run on a small background runtime, so these also work on blocking threads of another tokio runtime.
+/// All requests run on that runtime, so its client keeps the connection pool between calls.
 #[cfg(feature = "blocking")]
 pub mod blocking {
+    use std::{future::Future, sync::OnceLock};
+
     use mod_util::mod_info::Version;
 
-    pub use auth::*;
-    mod auth {
-        use std::collections::HashMap;
-
-        pub fn auth(
-            username: &str,
-            password: &str,
-        ) -> Result<crate::AuthDetails, crate::FactorioApiError> {
-            let body: HashMap<&str, &str> = [
-                ("username", username),
-                ("password", password),
-                ("api_version", "4"),
-            ]
-            .iter()
-            .copied()
-            .collect();
-
-            let client = reqwest::blocking::Client::new();
-            let res = client
-                .post("https://auth.factorio.com/api-login")
-                .form(&body)
-                .send()?;
-
-            Ok(serde_json::from_str(&res.text()?)?)
-        }
+    use crate::{Client, FactorioApiError};
+
+    fn client() -> &'static Client {
+        static CLIENT: OnceLock<Client> = OnceLock::new();
+        CLIENT.get_or_init(Client::default)
     }
 
-    pub use portal::*;
-    mod portal {
-        use crate::{PortalResponse, DEFAULT_ENDPOINT};
-
-        pub fn portal_list(
-            params: crate::PortalListParams,
-        ) -> Result<crate::PortalListResponse, crate::FactorioApiError> {
-            let endpoint = std::env::var("FACTORIO_API_ENDPOINT").ok();
-            let client = reqwest::blocking::Client::new();
-            let res = client
-                .get(format!(
-                    "{}/api/mods?{}",
-                    endpoint.unwrap_or_else(|| DEFAULT_ENDPOINT.to_owned()),
-                    params.build()
-                ))
-                .send()?;
-
-            match serde_json::from_slice(&res.bytes()?)? {
-                PortalResponse::Ok(res) => Ok(res),
-                PortalResponse::Err { message } => Err(crate::FactorioApiError::ApiError(message)),
-            }
-        }
+    fn block_on<F: Future>(future: F) -> Result<F::Output, FactorioApiError> {
+        static RUNTIME: OnceLock<tokio::runtime::Runtime> = OnceLock::new();
 
-        pub fn short_info(
-            mod_name: &str,
-        ) -> Result<crate::PortalShortEntry, crate::FactorioApiError> {
-            let endpoint = std::env::var("FACTORIO_API_ENDPOINT").ok();
-            let client = reqwest::blocking::Client::new();
-            let res = client
-                .get(format!(
-                    "{}/api/mods/{mod_name}",
-                    endpoint.unwrap_or_else(|| DEFAULT_ENDPOINT.to_owned())
-                ))
-                .send()?;
-
-            match serde_json::from_slice(&res.bytes()?)? {
-                PortalResponse::Ok(res) => Ok(res),
-                PortalResponse::Err { message } => Err(crate::FactorioApiError::ApiError(message)),
-            }
-        }
+        let runtime = if let Some(runtime) = RUNTIME.get() {
+            runtime
+        } else {
+            let runtime = tokio::runtime::Builder::new_multi_thread()
+                .worker_threads(1)
+                .thread_name("factorio-api")
+                .enable_all()
+                .build()?;
+            RUNTIME.get_or_init(|| runtime)
+        };
 
-        pub fn full_info(
-            mod_name: &str,
-        ) -> Result<crate::PortalLongEntry, crate::FactorioApiError> {
-            let endpoint = std::env::var("FACTORIO_API_ENDPOINT").ok();
-            let client = reqwest::blocking::Client::new();
-            let res = client
-                .get(format!(
-                    "{}/api/mods/{mod_name}/full",
-                    endpoint.unwrap_or_else(|| DEFAULT_ENDPOINT.to_owned())
-                ))
-                .send()?;
-
-            match serde_json::from_slice(&res.bytes()?)? {
-                PortalResponse::Ok(res) => Ok(res),
-                PortalResponse::Err { message } => Err(crate::FactorioApiError::ApiError(message)),
-            }
-        }
+        Ok(runtime.block_on(future))
+    }
+
+    pub fn auth(username: &str, password: &str) -> Result<crate::AuthDetails, FactorioApiError> {
+        block_on(client().auth(username, password))?
+    }
+
+    pub fn portal_list(
+        params: crate::PortalListParams,
+    ) -> Result<crate::PortalListResponse, FactorioApiError> {
+        block_on(client().portal_list(params))?
+    }
+
+    pub fn short_info(mod_name: &str) -> Result<crate::PortalShortEntry, FactorioApiError> {
+        block_on(client().short_info(mod_name))?
+    }
+
+    pub fn full_info(mod_name: &str) -> Result<crate::PortalLongEntry, FactorioApiError> {
+        block_on(client().full_info(mod_name))?
+    }
+
+    /// Full info of a mod through a [`crate::PortalCache`], see [`crate::PortalCache::full_info`].
+    pub fn cached_full_info(
+        cache: &crate::PortalCache,
+        mod_name: &str,
+    ) -> Result<crate::PortalLongEntry, FactorioApiError> {
+        block_on(cache.full_info(client(), mod_name))?
     }
 
     pub fn fetch_mod_raw(
         download_url: &str,
         username: &str,
         token: &str,
-    ) -> Result<Vec<u8>, crate::FactorioApiError> {
-        let client = reqwest::blocking::Client::new();
-        let res = client
-            .get(format!(
-                "https://mods.factorio.com{download_url}?username={username}&token={token}",
-            ))
-            .send()?;
-
-        Ok(res.bytes()?.to_vec())
+    ) -> Result<Vec<u8>, FactorioApiError> {
+        block_on(client().fetch_mod_raw(download_url, username, token))?
     }
 
+    /// Downloads a release of a mod and verifies it with [`crate::verify_release`].
     pub fn fetch_mod(
         mod_name: &str,
         version: &Version,
         username: &str,
         token: &str,
-    ) -> Result<Vec<u8>, crate::FactorioApiError> {
-        let mod_info = short_info(mod_name)?;
-
-        for release in mod_info.releases {
-            if release.version != *version {
-                continue;
-            }
-
-            return fetch_mod_raw(&release.download_url, username, token);
-        }
+    ) -> Result<Vec<u8>, FactorioApiError> {
+        block_on(client().fetch_mod(mod_name, version, username, token))?
+    }
 
-        Err(crate::FactorioApiError::NoRelease(mod_name.to_owned()))
+    /// Downloads multiple mods in parallel, see [`Client::download_mods`].
+    pub fn download_mods<F: Fn(&crate::ModDownload, crate::DownloadProgress)>(
+        downloads: &[crate::ModDownload],
+        credentials: (&str, &str),
+        workers: usize,
+        attempts: u32,
+        progress: F,
+    ) -> Result<Vec<Result<(), FactorioApiError>>, FactorioApiError> {
+        block_on(client().download_mods(downloads, credentials, workers, attempts, progress))
     }
 
     pub fn fetch_mod_with_password(
@@ -161,9 +188,8 @@ pub mod blocking {
         version: &Version,
         username: &str,
         password: &str,
-    ) -> Result<Vec<u8>, crate::FactorioApiError> {
-        let auth_res = auth(username, password)?;
-        fetch_mod(mod_name, version, &auth_res.username, &auth_res.token)
+    ) -> Result<Vec<u8>, FactorioApiError> {
+        block_on(client().fetch_mod_with_password(mod_name, version, username, password))?
     }
 
     #[cfg(test)]
@@ -308,8 +334,6 @@ pub mod blocking {
 
 pub use auth::*;
 mod auth {
-    use std::collections::HashMap;
-
     use serde::{Deserialize, Serialize};
 
     #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -322,23 +346,7 @@ mod auth {
         username: &str,
         password: &str,
     ) -> Result<AuthDetails, crate::FactorioApiError> {
-        let body: HashMap<&str, &str> = [
-            ("username", username),
-            ("password", password),
-            ("api_version", "4"),
-        ]
-        .iter()
-        .copied()
-        .collect();
-
-        let client = reqwest::Client::new();
-        let res = client
-            .post("https://auth.factorio.com/api-login")
-            .form(&body)
-            .send()
-            .await?;
-
-        Ok(serde_json::from_slice(&res.bytes().await?)?)
+        crate::Client::shared().auth(username, password).await
     }
 }
 
@@ -349,8 +357,6 @@ mod portal {
     use mod_util::mod_info::Version;
     use serde::{Deserialize, Serialize};
 
-    use crate::{PortalResponse, DEFAULT_ENDPOINT};
-
     #[derive(Debug, Copy, Clone, Deserialize)]
     #[serde(untagged)]
     pub enum PortalSearchPageSize {
@@ -617,21 +623,7 @@ mod portal {
     pub async fn portal_list(
         params: PortalListParams,
     ) -> Result<PortalListResponse, crate::FactorioApiError> {
-        let endpoint = std::env::var("FACTORIO_API_ENDPOINT").ok();
-        let client = reqwest::Client::new();
-        let res = client
-            .get(format!(
-                "{}/api/mods?{}",
-                endpoint.unwrap_or_else(|| DEFAULT_ENDPOINT.to_owned()),
-                params.build()
-            ))
-            .send()
-            .await?;
-
-        match serde_json::from_slice(&res.bytes().await?)? {
-            PortalResponse::Ok(res) => Ok(res),
-            PortalResponse::Err { message } => Err(crate::FactorioApiError::ApiError(message)),
-        }
+        crate::Client::shared().portal_list(params).await
     }
 
     #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -649,20 +641,7 @@ mod portal {
     }
 
     pub async fn short_info(mod_name: &str) -> Result<PortalShortEntry, crate::FactorioApiError> {
-        let endpoint = std::env::var("FACTORIO_API_ENDPOINT").ok();
-        let client = reqwest::Client::new();
-        let res = client
-            .get(format!(
-                "{}/api/mods/{mod_name}",
-                endpoint.unwrap_or_else(|| DEFAULT_ENDPOINT.to_owned())
-            ))
-            .send()
-            .await?;
-
-        match serde_json::from_slice(&res.bytes().await?)? {
-            PortalResponse::Ok(res) => Ok(res),
-            PortalResponse::Err { message } => Err(crate::FactorioApiError::ApiError(message)),
-        }
+        crate::Client::shared().short_info(mod_name).await
     }
 
     #[derive(Debug, Deserialize, Serialize, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -737,20 +716,7 @@ mod portal {
     }
 
     pub async fn full_info(mod_name: &str) -> Result<PortalLongEntry, crate::FactorioApiError> {
-        let endpoint = std::env::var("FACTORIO_API_ENDPOINT").ok();
-        let client = reqwest::Client::new();
-        let res = client
-            .get(format!(
-                "{}/api/mods/{mod_name}/full",
-                endpoint.unwrap_or_else(|| DEFAULT_ENDPOINT.to_owned())
-            ))
-            .send()
-            .await?;
-
-        match serde_json::from_slice(&res.bytes().await?)? {
-            PortalResponse::Ok(res) => Ok(res),
-            PortalResponse::Err { message } => Err(crate::FactorioApiError::ApiError(message)),
-        }
+        crate::Client::shared().full_info(mod_name).await
     }
 }
 
@@ -759,34 +725,21 @@ pub async fn fetch_mod_raw(
     username: &str,
     token: &str,
 ) -> Result<Vec<u8>, FactorioApiError> {
-    let client = reqwest::Client::new();
-    let res = client
-        .get(format!(
-            "https://mods.factorio.com{download_url}?username={username}&token={token}"
-        ))
-        .send()
-        .await?;
-
-    Ok(res.bytes().await?.to_vec())
+    Client::shared()
+        .fetch_mod_raw(download_url, username, token)
+        .await
 }
 
+/// Downloads a release of a mod and verifies it with [`verify_release`].
 pub async fn fetch_mod(
     mod_name: &str,
     version: &Version,
     username: &str,
     token: &str,
 ) -> Result<Vec<u8>, FactorioApiError> {
-    let mod_info = short_info(mod_name).await?;
-
-    for release in mod_info.releases {
-        if release.version != *version {
-            continue;
-        }
-
-        return fetch_mod_raw(&release.download_url, username, token).await;
-    }
-
-    Err(FactorioApiError::NoRelease(mod_name.to_owned()))
+    Client::shared()
+        .fetch_mod(mod_name, version, username, token)
+        .await
 }
 
 pub async fn fetch_mod_with_password(
@@ -795,14 +748,54 @@ pub async fn fetch_mod_with_password(
     username: &str,
     password: &str,
 ) -> Result<Vec<u8>, FactorioApiError> {
-    let auth_res = auth(username, password).await?;
-    fetch_mod(mod_name, version, &auth_res.username, &auth_res.token).await
+    Client::shared()
+        .fetch_mod_with_password(mod_name, version, username, password)
+        .await
 }
 
 #[cfg(test)]
 mod tests {
+    #![allow(clippy::unwrap_used)]
+
     use super::*;
 
+    fn release_with_sha1(sha1: &str) -> ModRelease {
+        serde_json::from_value(serde_json::json!({
+            "download_url": "/download/test/1",
+            "file_name": "test_1.0.0.zip",
+            "info_json": { "factorio_version": "1.1" },
+            "released_at": "2024-01-01T00:00:00.000000Z",
+            "version": "1.0.0",
+            "sha1": sha1,
+        }))
+        .unwrap()
+    }
+
+    fn zip_bytes() -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        writer
+            .start_file("test_1.0.0/info.json", zip::write::FileOptions::default())
+            .unwrap();
+        std::io::Write::write_all(&mut writer, b"{}").unwrap();
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn verify_release_checks_zip_and_sha1() {
+        let data = zip_bytes();
+        let sha1 = format!("{:x}", Sha1::digest(&data));
+
+        assert!(verify_release(&release_with_sha1(&sha1), &data).is_ok());
+        assert!(matches!(
+            verify_release(&release_with_sha1("0000"), &data),
+            Err(FactorioApiError::HashMismatch { .. })
+        ));
+        assert!(matches!(
+            verify_release(&release_with_sha1(&sha1), b"<html>login</html>"),
+            Err(FactorioApiError::InvalidZip(_))
+        ));
+    }
+
     #[test]
     fn invalid_auth() {
         let result = tokio_test::block_on(auth(
@@ -0,0 +1,41 @@
+//! Minimal HTTP server for tests that need the portal to answer in a specific way.
+
+#![allow(clippy::unwrap_used)]
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::TcpListener,
+    sync::mpsc,
+};
+
+/// Answers the given responses in order, one per connection. The request heads are sent back through the channel.
+pub fn stand_in_server(responses: Vec<Vec<u8>>) -> (String, mpsc::Receiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (requests_tx, requests_rx) = mpsc::channel();
+
+    std::thread::spawn(move || {
+        for response in responses {
+            let (mut stream, _) = listener.accept().unwrap();
+
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut head = String::new();
+            while reader.read_line(&mut head).unwrap() > 0 && !head.ends_with("\r\n\r\n") {}
+            requests_tx.send(head.to_lowercase()).ok();
+
+            stream.write_all(&response).unwrap();
+        }
+    });
+
+    (format!("http://{addr}"), requests_rx)
+}
+
+pub fn response(status: &str, headers: &str, body: &[u8]) -> Vec<u8> {
+    let mut res = format!(
+        "HTTP/1.1 {status}\r\nconnection: close\r\ncontent-length: {}\r\n{headers}\r\n",
+        body.len()
+    )
+    .into_bytes();
+    res.extend_from_slice(body);
+    res
+}
@@ -531,11 +531,17 @@ impl DependencyUtil for &Dependency {
 
 impl fmt::Display for Dependency {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let prefix = &self.kind;
-        let name = &self.name;
-        let version = &self.version;
+        if self.kind != DependencyType::Required {
+            write!(f, "{} ", self.kind)?;
+        }
+
+        write!(f, "{}", self.name)?;
+
+        if self.version != DependencyVersion::Any {
+            write!(f, " {}", self.version)?;
+        }
 
-        write!(f, "{prefix} {name}{version}")
+        Ok(())
     }
 }
 
@@ -656,3 +662,25 @@ pub trait DependencyExt: Iterator + Sized {
 }
 
 impl<I: Iterator> DependencyExt for I {}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    #[test]
+    fn dependency_display_round_trips() {
+        for dep in [
+            "base",
+            "base >= 1.1.0",
+            "? foo < 2.0.0",
+            "! bar",
+            "(?) baz",
+            "~ qux = 1.0.0",
+        ] {
+            let parsed: Dependency = serde_json::from_value(dep.into()).unwrap();
+            assert_eq!(parsed.to_string(), dep);
+        }
+    }
+}
@@ -5,13 +5,12 @@ use std::{
     path::Path,
 };
 
-use petgraph::prelude::DiGraph;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 
 use crate::{
-    mod_info::{Dependency, DependencyExt, DependencyUtil, DependencyVersion, Version},
+    mod_info::{Dependency, DependencyUtil, DependencyVersion, Version},
     mod_loader::Mod,
     DependencyList, UsedMods, UsedVersions,
 };
@@ -30,26 +29,11 @@ pub enum ModListError {
     #[error("dependency solver could not find info about {0}")]
     SolverMissingInfo(String),
 
-    #[error("dependency solver could not find dependencies for {0} v{1}")]
-    SolverNoDependencies(String, Version),
+    #[error("dependency solver could not satisfy the dependencies: {0}")]
+    SolverUnsatisfiable(String),
 
-    #[error("dependency solver could not find info about dep {0}")]
-    SolverNoInfoOnDependency(String),
-
-    #[error("dependency solver could not find a version for {0} that satisfies {1}")]
-    SolverUnsatisfiable(String, DependencyVersion),
-
-    #[error("dependency solver could not find info about {0} while building edges")]
-    SolverEdgesMissingInfo(String),
-
-    #[error("dependency solver could not find dependencies for {0} v{1} while building edges")]
-    SolverEdgesNoInfoOnDependency(String, Version),
-
-    #[error("dependency solver could not find required node for {0} while building edges")]
-    SolverEdgesNodeNotFound(String),
-
-    #[error("dependency solver found conflicts for {0} v{1}: {2:?}")]
-    SolverFoundConflicts(String, Version, Vec<Dependency>),
+    #[error("dependency solver gave up after trying {0} combinations")]
+    SolverGaveUp(usize),
 }
 
 type Result<T> = std::result::Result<T, ModListError>;
@@ -395,155 +379,336 @@ impl<'a> ModList<'a> {
         e.known_dependencies = known_dependencies;
     }
 
-    #[allow(clippy::too_many_lines)]
+    /// Picks a version for every required mod and all of their (transitive) required dependencies.
+    ///
+    /// Newer versions are preferred, older ones are only tried when the newer ones lead to a conflict.
+    /// Optional dependencies don't pull in mods but restrict their versions if they end up being used,
+    /// incompatible dependencies must not end up being used at all.
+    /// Only versions with known dependency info are considered.
     pub fn solve_dependencies(&self, required: &DependencyList) -> Result<UsedVersions> {
         if required.is_empty() {
             return Ok(UsedVersions::default());
         }
 
-        // TODO: actually solve the constraints and not just check if the latest versions work
-        let mut dep_graph = DiGraph::<(&str, Version), &Dependency>::new();
-
-        let all_deps = self
-            .list
-            .iter()
-            .map(|(name, entry)| (name.as_str(), &entry.known_dependencies))
-            .collect::<HashMap<_, _>>();
-
-        let mut reqs = Vec::new();
+        let mut root = Vec::with_capacity(required.len());
         for (name, version) in required {
-            let Some(info) = self.list.get(name) else {
+            if !self.list.contains_key(name) {
                 return Err(ModListError::SolverMissingInfo(name.to_string()));
-            };
+            }
 
-            let info_versions = info.versions.keys().copied().collect::<Vec<_>>();
+            // the wube mods are bundled with the game, there is no other version to pick
             if Mod::wube_mods().contains(&name.as_str()) {
-                let Some(version) = info_versions.first() else {
-                    return Err(ModListError::SolverMissingInfo(format!(
-                        "{name}, a static wube mod!"
-                    )));
-                };
+                root.push((name.as_str(), DependencyVersion::Any));
+            } else {
+                root.push((name.as_str(), *version));
+            }
+        }
+        root.sort_unstable_by_key(|(name, _)| *name);
+
+        let mut solver = Solver {
+            known: self
+                .list
+                .iter()
+                .map(|(name, entry)| (name.as_str(), &entry.known_dependencies))
+                .collect(),
+            root,
+            selected: Vec::new(),
+            steps: 0,
+            failure: None,
+        };
+
+        if solver.search()? {
+            return Ok(solver
+                .selected
+                .into_iter()
+                .map(|(name, version)| (name.to_owned(), version))
+                .collect());
+        }
 
-                reqs.push((name.as_str(), *version));
-                continue;
+        Err(ModListError::SolverUnsatisfiable(
+            solver
+                .failure
+                .map(|(_, explanation)| explanation)
+                .unwrap_or_default(),
+        ))
+    }
+}
+
+/// Upper bound of tried (partial) assignments before the solver gives up.
+const MAX_SOLVER_STEPS: usize = 100_000;
+
+/// Backtracking search over the known dependencies of all mods.
+struct Solver<'a> {
+    known: HashMap<&'a str, &'a HashMap<Version, Vec<Dependency>>>,
+    root: Vec<(&'a str, DependencyVersion)>,
+
+    /// Mods with their picked version in the order they were picked.
+    selected: Vec<(&'a str, Version)>,
+
+    steps: usize,
+
+    /// Explanation of the failure that happened with the most mods picked, since that is usually the most relevant one.
+    failure: Option<(usize, String)>,
+}
+
+impl<'a> Solver<'a> {
+    fn selected_version(&self, name: &str) -> Option<Version> {
+        self.selected
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, v)| *v)
+    }
+
+    fn dependencies(&self, name: &str, version: Version) -> &'a [Dependency] {
+        self.known
+            .get(name)
+            .and_then(|versions| versions.get(&version))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Mods that are required by the root or a picked mod but have no version picked yet.
+    fn pending(&self) -> Vec<&'a str> {
+        let mut pending = Vec::new();
+
+        let required = self.root.iter().map(|(name, _)| *name).chain(
+            self.selected
+                .iter()
+                .flat_map(|(name, version)| self.dependencies(name, *version))
+                .filter(|dep| dep.is_required())
+                .map(|dep| dep.name().as_str()),
+        );
+
+        for name in required {
+            if self.selected_version(name).is_none() && !pending.contains(&name) {
+                pending.push(name);
             }
+        }
 
-            let Some(version) = version.get_allowed_version(&info_versions) else {
-                return Err(ModListError::SolverMissingInfo(name.to_string()));
-            };
+        pending
+    }
 
-            reqs.push((name.as_str(), *version));
+    /// Why `version` of `name` can't be picked with the current selection.
+    fn rejection(&self, name: &str, version: Version) -> Option<String> {
+        for (root_name, root_version) in &self.root {
+            if *root_name == name && !root_version.allows(version) {
+                return Some(format!("requested {name} {root_version}"));
+            }
         }
 
-        // build all required mod nodes
-        let mut node_map = HashMap::new();
-        while let Some((name, version)) = reqs.pop() {
-            if node_map.contains_key(&name) {
-                continue;
+        for (other, other_version) in &self.selected {
+            for dep in self.dependencies(other, *other_version) {
+                if !dep.conflicts(name, version) {
+                    continue;
+                }
+
+                return Some(if dep.is_incompatible() {
+                    format!("{other} v{other_version} is incompatible with {name}")
+                } else {
+                    format!(
+                        "{other} v{other_version} depends on {name} {}",
+                        dep.version()
+                    )
+                });
             }
+        }
 
-            node_map.insert(name, dep_graph.add_node((name, version)));
+        for dep in self.dependencies(name, version) {
+            let dep_name = dep.name().as_str();
+            let Some(dep_version) = self.selected_version(dep_name) else {
+                if dep.is_required() && !self.known.contains_key(dep_name) {
+                    return Some(format!("depends on {dep_name}, which is unknown"));
+                }
 
-            let Some(info) = all_deps.get(name) else {
-                return Err(ModListError::SolverMissingInfo(name.to_string()));
+                continue;
             };
 
-            let Some(node_reqs) = info.get(&version) else {
-                return Err(ModListError::SolverNoDependencies(
-                    name.to_string(),
-                    version,
-                ));
-            };
+            if !dep.conflicts(dep_name, dep_version) {
+                continue;
+            }
 
-            // add required dependencies to the reqs queue
-            for dep in node_reqs {
-                if !dep.is_required() {
-                    continue;
-                }
+            return Some(if dep.is_incompatible() {
+                format!("incompatible with {dep_name} v{dep_version}")
+            } else {
+                format!(
+                    "depends on {dep_name} {} but v{dep_version} is used",
+                    dep.version()
+                )
+            });
+        }
 
-                let dep_name = dep.name().as_str();
-                let dep_version = dep.version();
+        None
+    }
 
-                let Some(info) = all_deps.get(dep_name) else {
-                    return Err(ModListError::SolverNoInfoOnDependency(dep_name.to_string()));
-                };
+    /// Versions of `name` that can be picked with the current selection, newest first.
+    fn candidates(&self, name: &str) -> Vec<Version> {
+        let Some(known) = self.known.get(name) else {
+            return Vec::new();
+        };
+
+        let mut versions = known
+            .keys()
+            .copied()
+            .filter(|version| self.rejection(name, *version).is_none())
+            .collect::<Vec<_>>();
+        versions.sort_unstable_by(|a, b| b.cmp(a));
+        versions
+    }
 
-                let mut dep_versions = info
-                    .keys()
-                    .filter(|v| dep.allows(dep_name, **v))
+    fn record_failure(&mut self, name: &str) {
+        if self
+            .failure
+            .as_ref()
+            .is_some_and(|(depth, _)| *depth >= self.selected.len())
+        {
+            return;
+        }
+
+        let mut explanation = match self.known.get(name) {
+            Some(known) if !known.is_empty() => {
+                let mut versions = known.keys().copied().collect::<Vec<_>>();
+                versions.sort_unstable_by(|a, b| b.cmp(a));
+
+                let reasons = versions
+                    .into_iter()
+                    .filter_map(|version| {
+                        self.rejection(name, version)
+                            .map(|reason| format!("v{version}: {reason}"))
+                    })
                     .collect::<Vec<_>>();
-                dep_versions.sort();
-
-                let Some(dep_version) = dep_versions.last() else {
-                    // no more versions to try, fail?
-                    return Err(ModListError::SolverUnsatisfiable(
-                        dep_name.to_string(),
-                        *dep_version,
-                    ));
-                };
 
-                reqs.push((dep_name, **dep_version));
+                format!("no version of {name} can be used ({})", reasons.join("; "))
             }
+            _ => format!("no dependency info about {name} is known"),
+        };
+
+        if !self.selected.is_empty() {
+            let picked = self
+                .selected
+                .iter()
+                .map(|(n, v)| format!("{n} v{v}"))
+                .collect::<Vec<_>>();
+            explanation.push_str(" with ");
+            explanation.push_str(&picked.join(", "));
         }
 
-        // add all dependency edges
-        for node in dep_graph.node_indices() {
-            let (name, version) = &dep_graph[node];
-            let Some(info) = all_deps.get(name) else {
-                return Err(ModListError::SolverEdgesMissingInfo((*name).to_string()));
-            };
+        self.failure = Some((self.selected.len(), explanation));
+    }
 
-            let Some(deps) = info.get(version) else {
-                return Err(ModListError::SolverEdgesNoInfoOnDependency(
-                    (*name).to_string(),
-                    *version,
-                ));
-            };
+    fn search(&mut self) -> Result<bool> {
+        self.steps += 1;
+        if self.steps > MAX_SOLVER_STEPS {
+            return Err(ModListError::SolverGaveUp(MAX_SOLVER_STEPS));
+        }
 
-            for dep in deps {
-                let dep_name = dep.name().as_str();
+        // pick the mod with the fewest options first, dead ends are found earlier that way
+        let mut next: Option<(&str, Vec<Version>)> = None;
+        for name in self.pending() {
+            let candidates = self.candidates(name);
+            if candidates.is_empty() {
+                self.record_failure(name);
+                return Ok(false);
+            }
 
-                match node_map.get(dep_name) {
-                    Some(dep_node) => {
-                        dep_graph.add_edge(node, *dep_node, dep);
-                    }
-                    None => {
-                        // optional / incompatible dependencies are allowed to be missing
-                        if dep.is_required() {
-                            return Err(ModListError::SolverEdgesNodeNotFound(
-                                dep_name.to_string(),
-                            ));
-                        }
-                    }
-                }
+            if next
+                .as_ref()
+                .is_none_or(|(_, best)| candidates.len() < best.len())
+            {
+                next = Some((name, candidates));
             }
         }
 
-        // check if all requirements are satisfied
-        for node in dep_graph.node_indices() {
-            let (name, version) = &dep_graph[node];
-
-            let conflicts = dep_graph
-                .edges_directed(node, petgraph::Direction::Incoming)
-                .map(|e| *e.weight())
-                .cloned()
-                .collect_conflicts::<Vec<_>>(name, *version);
-
-            if !conflicts.is_empty() {
-                return Err(ModListError::SolverFoundConflicts(
-                    (*name).to_string(),
-                    *version,
-                    conflicts,
-                ));
+        let Some((name, candidates)) = next else {
+            // nothing left to pick, every constraint is satisfied
+            return Ok(true);
+        };
+
+        for version in candidates {
+            self.selected.push((name, version));
+            if self.search()? {
+                return Ok(true);
             }
+            self.selected.pop();
         }
 
-        // dependencies are satisfied, hurray!
-        // collect all used mods + versions
-        Ok(dep_graph
-            .raw_nodes()
-            .iter()
-            .map(|n| (n.weight.0.to_string(), n.weight.1))
-            .collect())
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    fn mod_list(mods: &[(&str, &str, &[&str])]) -> ModList<'static> {
+        let mut list = ModList {
+            factorio_dir: Path::new(""),
+            list: HashMap::new(),
+        };
+
+        for (name, version, deps) in mods {
+            let version = Version::try_from(*version).unwrap();
+            let deps = deps
+                .iter()
+                .map(|dep| serde_json::from_value(serde_json::Value::from(*dep)).unwrap())
+                .collect();
+
+            let entry = list.list.entry((*name).to_owned()).or_default();
+            entry.versions.insert(version, None);
+            entry.known_dependencies.insert(version, deps);
+        }
+
+        list
+    }
+
+    fn required(mods: &[&str]) -> DependencyList {
+        mods.iter()
+            .map(|name| ((*name).to_owned(), DependencyVersion::Any))
+            .collect()
+    }
+
+    #[test]
+    fn picks_older_release_on_conflict() {
+        let list = mod_list(&[
+            ("lib", "2.0.0", &[]),
+            ("lib", "1.0.0", &[]),
+            ("a", "1.0.0", &["lib >= 1.0.0"]),
+            ("b", "1.0.0", &["lib < 2.0.0"]),
+        ]);
+
+        let solved = list.solve_dependencies(&required(&["a", "b"])).unwrap();
+        assert_eq!(solved["lib"], Version::new(1, 0, 0));
+        assert_eq!(solved.len(), 3);
+    }
+
+    #[test]
+    fn respects_optional_and_incompatible() {
+        let list = mod_list(&[
+            ("a", "2.0.0", &["! b"]),
+            ("a", "1.0.0", &["? c >= 2.0.0"]),
+            ("b", "1.0.0", &[]),
+            ("c", "1.0.0", &[]),
+        ]);
+
+        let solved = list.solve_dependencies(&required(&["a", "b"])).unwrap();
+        assert_eq!(solved["a"], Version::new(1, 0, 0));
+        assert!(!solved.contains_key("c"));
+
+        assert!(list
+            .solve_dependencies(&required(&["a", "b", "c"]))
+            .is_err());
+    }
+
+    #[test]
+    fn explains_unsatisfiable() {
+        let list = mod_list(&[("lib", "1.0.0", &[]), ("a", "1.0.0", &["lib >= 2.0.0"])]);
+
+        let Err(ModListError::SolverUnsatisfiable(explanation)) =
+            list.solve_dependencies(&required(&["a"]))
+        else {
+            panic!("expected the solver to fail");
+        };
+
+        assert!(explanation.contains("a v1.0.0 depends on lib >= 2.0.0"));
     }
 }
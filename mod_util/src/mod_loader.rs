@@ -1,8 +1,8 @@
 use std::{
-    cell::RefCell,
     fs::File,
     io::Read,
     path::{Path, PathBuf},
+    sync::Mutex,
 };
 
 use zip::ZipArchive;
@@ -38,8 +38,8 @@ pub enum ModError {
     #[error("mod zip error: {0}")]
     ZipError(#[from] zip::result::ZipError),
 
-    #[error("mod mutable borrow error: {0}")]
-    BorrowError(#[from] std::cell::BorrowMutError),
+    #[error("mod zip lock was poisoned")]
+    ZipLockPoisoned,
 }
 
 type Result<T> = std::result::Result<T, ModError>;
@@ -114,7 +114,7 @@ enum ModType {
     },
     Zip {
         internal_prefix: String,
-        zip: RefCell<ZipArchive<File>>,
+        zip: Mutex<ZipArchive<File>>,
     },
 }
 
@@ -140,7 +140,7 @@ impl ModType {
 
             Ok(Self::Zip {
                 internal_prefix,
-                zip: RefCell::new(zip),
+                zip: Mutex::new(zip),
             })
         } else {
             return Err(ModError::PathNotZipOrDir(path));
@@ -162,7 +162,7 @@ impl ModType {
                 zip,
             } => {
                 let path = internal_prefix.clone() + file;
-                let mut zip = zip.try_borrow_mut()?;
+                let mut zip = zip.lock().map_err(|_| ModError::ZipLockPoisoned)?;
                 let mut file = zip.by_name(&path)?;
 
                 // if the vec allocates not enough it will just reallocate
@@ -170,6 +170,10 @@ impl ModType {
                 let mut bytes = Vec::with_capacity(file.size() as usize);
 
                 file.read_to_end(&mut bytes)?;
+                // the entry borrows the archive, both have to go before the lock is released
+                drop(file);
+                drop(zip);
+
                 Ok(bytes)
             }
         }
@@ -118,6 +118,8 @@ pub struct RenderOpts {
     pub logistic_connected: bool,
 
     pub fluid_recipe: (bool, bool),
+
+    pub modules: Vec<InsertedModule>,
 }
 
 // From impls for RenderOpts variants from types
@@ -213,6 +215,7 @@ impl From<&RenderOpts> for BeaconGraphicsSetRenderOpts {
     fn from(value: &RenderOpts) -> Self {
         Self {
             runtime_tint: value.runtime_tint,
+            modules: value.modules.iter().cloned().map(Some).collect(),
         }
     }
 }
@@ -252,6 +255,11 @@ pub trait Renderable {
 
     fn fluid_box_connections(&self, options: &RenderOpts) -> Vec<MapPosition>;
     fn heat_buffer_connections(&self, options: &RenderOpts) -> Vec<MapPosition>;
+
+    /// Fluid box connections together with their flow type that are shown as arrows in alt-mode.
+    fn fluid_box_indicators(&self, options: &RenderOpts) -> Vec<(MapPosition, PipeConnectionType)> {
+        Vec::new()
+    }
 }
 
 /// [`Prototypes/EntityPrototype`](https://lua-api.factorio.com/latest/prototypes/EntityPrototype.html)
@@ -285,6 +293,10 @@ impl<T: Renderable> Renderable for EntityPrototype<T> {
     fn heat_buffer_connections(&self, options: &RenderOpts) -> Vec<MapPosition> {
         self.child.heat_buffer_connections(options)
     }
+
+    fn fluid_box_indicators(&self, options: &RenderOpts) -> Vec<(MapPosition, PipeConnectionType)> {
+        self.child.fluid_box_indicators(options)
+    }
 }
 
 pub trait RenderableEntity: Renderable {
@@ -294,6 +306,12 @@ pub trait RenderableEntity: Renderable {
 
     fn pipe_connections(&self, options: &RenderOpts) -> Vec<(MapPosition, Direction)>;
     fn heat_connections(&self, options: &RenderOpts) -> Vec<(MapPosition, Direction)>;
+
+    /// Absolute positions of the alt-mode fluid arrows, the direction points towards the entity.
+    fn fluid_indicators(
+        &self,
+        options: &RenderOpts,
+    ) -> Vec<(MapPosition, Direction, PipeConnectionType)>;
 }
 
 impl<R, T> RenderableEntity for T
@@ -316,38 +334,12 @@ where
     }
 
     fn pipe_connections(&self, options: &RenderOpts) -> Vec<(MapPosition, Direction)> {
-        let raw_connections = self.fluid_box_connections(options);
+        let collision_box = self.collision_box();
 
-        if raw_connections.is_empty() {
-            return Vec::new();
-        }
-
-        let BoundingBox(tl, br) = self.collision_box();
-        let tl_vec: Vector = tl.into();
-        let br_vec: Vector = br.into();
-        let (tl_x, tl_y) = options.direction.rotate_vector(tl_vec).as_tuple();
-        let (br_x, br_y) = options.direction.rotate_vector(br_vec).as_tuple();
-
-        let top_y = tl_y.min(br_y);
-        let bottom_y = tl_y.max(br_y);
-        let left_x = tl_x.min(br_x);
-        let right_x = tl_x.max(br_x);
-
-        raw_connections
-            .iter()
+        self.fluid_box_connections(options)
+            .into_iter()
             .filter_map(|conn| {
-                let conn = conn.clone();
-                let (x, y) = conn.as_tuple();
-
-                let dir = if y <= top_y {
-                    Direction::South
-                } else if y >= bottom_y {
-                    Direction::North
-                } else if x <= left_x {
-                    Direction::East
-                } else if x >= right_x {
-                    Direction::West
-                } else {
+                let Some(dir) = connection_side(&collision_box, options.direction, &conn) else {
                     println!(
                         "Invalid pipe connection [{}] @ {:?}: {conn:?}",
                         self.name, options.direction
@@ -361,38 +353,12 @@ where
     }
 
     fn heat_connections(&self, options: &RenderOpts) -> Vec<(MapPosition, Direction)> {
-        let raw_connections = self.heat_buffer_connections(options);
-
-        if raw_connections.is_empty() {
-            return Vec::new();
-        }
-
-        let BoundingBox(tl, br) = self.collision_box();
-        let tl_vec: Vector = tl.into();
-        let br_vec: Vector = br.into();
-        let (tl_x, tl_y) = options.direction.rotate_vector(tl_vec).as_tuple();
-        let (br_x, br_y) = options.direction.rotate_vector(br_vec).as_tuple();
+        let collision_box = self.collision_box();
 
-        let top_y = tl_y.min(br_y);
-        let bottom_y = tl_y.max(br_y);
-        let left_x = tl_x.min(br_x);
-        let right_x = tl_x.max(br_x);
-
-        raw_connections
-            .iter()
+        self.heat_buffer_connections(options)
+            .into_iter()
             .filter_map(|conn| {
-                let conn = conn.clone();
-                let (x, y) = conn.as_tuple();
-
-                let dir = if y <= top_y {
-                    Direction::South
-                } else if y >= bottom_y {
-                    Direction::North
-                } else if x <= left_x {
-                    Direction::East
-                } else if x >= right_x {
-                    Direction::West
-                } else {
+                let Some(dir) = connection_side(&collision_box, options.direction, &conn) else {
                     println!(
                         "Invalid heat connection [{}] @ {:?}: {conn:?}",
                         self.name, options.direction
@@ -404,6 +370,54 @@ where
             })
             .collect()
     }
+
+    fn fluid_indicators(
+        &self,
+        options: &RenderOpts,
+    ) -> Vec<(MapPosition, Direction, PipeConnectionType)> {
+        let collision_box = self.collision_box();
+
+        self.fluid_box_indicators(options)
+            .into_iter()
+            .filter_map(|(conn, flow)| {
+                let dir = connection_side(&collision_box, options.direction, &conn)?;
+                Some((conn + &options.position, dir, flow))
+            })
+            .collect()
+    }
+}
+
+/// Side of the rotated collision box the connection point lies on, as the direction from the
+/// connection point towards the entity. `None` if the point is inside the collision box.
+fn connection_side(
+    collision_box: &BoundingBox,
+    direction: Direction,
+    conn: &MapPosition,
+) -> Option<Direction> {
+    let BoundingBox(tl, br) = collision_box.clone();
+    let tl_vec: Vector = tl.into();
+    let br_vec: Vector = br.into();
+    let (tl_x, tl_y) = direction.rotate_vector(tl_vec).as_tuple();
+    let (br_x, br_y) = direction.rotate_vector(br_vec).as_tuple();
+
+    let top_y = tl_y.min(br_y);
+    let bottom_y = tl_y.max(br_y);
+    let left_x = tl_x.min(br_x);
+    let right_x = tl_x.max(br_x);
+
+    let (x, y) = conn.as_tuple();
+
+    if y <= top_y {
+        Some(Direction::South)
+    } else if y >= bottom_y {
+        Some(Direction::North)
+    } else if x <= left_x {
+        Some(Direction::East)
+    } else if x >= right_x {
+        Some(Direction::West)
+    } else {
+        None
+    }
 }
 
 /// [`Prototypes/EntityPrototype`](https://lua-api.factorio.com/latest/prototypes/EntityPrototype.html)
@@ -542,6 +556,10 @@ impl<T: Renderable> Renderable for EntityData<T> {
     fn heat_buffer_connections(&self, options: &RenderOpts) -> Vec<MapPosition> {
         self.child.heat_buffer_connections(options)
     }
+
+    fn fluid_box_indicators(&self, options: &RenderOpts) -> Vec<(MapPosition, PipeConnectionType)> {
+        self.child.fluid_box_indicators(options)
+    }
 }
 
 #[derive(Debug, Default, PartialEq, Eq)]
@@ -670,6 +688,10 @@ impl<T: Renderable> Renderable for EntityWithHealthData<T> {
     fn heat_buffer_connections(&self, options: &RenderOpts) -> Vec<MapPosition> {
         self.child.heat_buffer_connections(options)
     }
+
+    fn fluid_box_indicators(&self, options: &RenderOpts) -> Vec<(MapPosition, PipeConnectionType)> {
+        self.child.fluid_box_indicators(options)
+    }
 }
 
 /// [`Prototypes/EntityWithHealthPrototype`](https://lua-api.factorio.com/latest/prototypes/EntityWithHealthPrototype.html)
@@ -715,6 +737,10 @@ impl<T: Renderable> Renderable for EntityWithOwnerData<T> {
     fn heat_buffer_connections(&self, options: &RenderOpts) -> Vec<MapPosition> {
         self.child.heat_buffer_connections(options)
     }
+
+    fn fluid_box_indicators(&self, options: &RenderOpts) -> Vec<(MapPosition, PipeConnectionType)> {
+        self.child.fluid_box_indicators(options)
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
@@ -69,4 +69,17 @@ impl<T: Renderable> Renderable for EnergyEntityData<T> {
 
         child
     }
+
+    fn fluid_box_indicators(
+        &self,
+        options: &crate::entity::RenderOpts,
+    ) -> Vec<(types::MapPosition, types::PipeConnectionType)> {
+        let mut child = self.child.fluid_box_indicators(options);
+
+        if let AnyEnergySource::Fluid { data } = &self.energy_source {
+            child.append(&mut data.fluid_box.indicated_connections(options.direction));
+        }
+
+        child
+    }
 }
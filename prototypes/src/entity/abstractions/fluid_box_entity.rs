@@ -40,4 +40,13 @@ impl<T: Renderable> Renderable for FluidBoxEntityData<T> {
     fn heat_buffer_connections(&self, options: &super::RenderOpts) -> Vec<types::MapPosition> {
         self.child.heat_buffer_connections(options)
     }
+
+    fn fluid_box_indicators(
+        &self,
+        options: &super::RenderOpts,
+    ) -> Vec<(types::MapPosition, types::PipeConnectionType)> {
+        let mut res = self.fluid_box.indicated_connections(options.direction);
+        res.append(&mut self.child.fluid_box_indicators(options));
+        res
+    }
 }
@@ -40,4 +40,11 @@ impl<T: Renderable> Renderable for HeatBufferEntityData<T> {
         res.append(&mut self.child.heat_buffer_connections(options));
         res
     }
+
+    fn fluid_box_indicators(
+        &self,
+        options: &super::RenderOpts,
+    ) -> Vec<(types::MapPosition, types::PipeConnectionType)> {
+        self.child.fluid_box_indicators(options)
+    }
 }
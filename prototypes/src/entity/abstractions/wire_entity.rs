@@ -72,4 +72,11 @@ impl<T: Renderable> Renderable for WireEntityData<T> {
     fn heat_buffer_connections(&self, options: &super::RenderOpts) -> Vec<types::MapPosition> {
         self.child.heat_buffer_connections(options)
     }
+
+    fn fluid_box_indicators(
+        &self,
+        options: &super::RenderOpts,
+    ) -> Vec<(types::MapPosition, types::PipeConnectionType)> {
+        self.child.fluid_box_indicators(options)
+    }
 }
@@ -34,12 +34,11 @@ impl super::Renderable for BeaconData {
         image_cache: &mut ImageCache,
     ) -> super::RenderOutput {
         let res = if let Some(set) = self.graphics_set.as_ref() {
-            set.render(
-                render_layers.scale(),
-                used_mods,
-                image_cache,
-                &options.into(),
-            )
+            let mut opts = BeaconGraphicsSetRenderOpts::from(options);
+            opts.modules
+                .resize(usize::from(self.module_specification.module_slots), None);
+
+            set.render(render_layers.scale(), used_mods, image_cache, &opts)
         } else {
             merge_renders(
                 &[
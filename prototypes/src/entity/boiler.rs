@@ -64,6 +64,14 @@ impl super::Renderable for BoilerData {
     fn heat_buffer_connections(&self, options: &super::RenderOpts) -> Vec<MapPosition> {
         Vec::with_capacity(0)
     }
+
+    fn fluid_box_indicators(
+        &self,
+        options: &super::RenderOpts,
+    ) -> Vec<(MapPosition, PipeConnectionType)> {
+        self.output_fluid_box
+            .indicated_connections(options.direction)
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -88,6 +88,20 @@ impl<T: super::Renderable> super::Renderable for CombinatorData<T> {
     }
 }
 
+/// Modded combinators without symbol sprites show their operation as text instead.
+fn render_symbol_text(
+    symbol: &str,
+    options: &super::RenderOpts,
+    render_layers: &mut crate::RenderLayerBuffer,
+) -> super::RenderOutput {
+    render_layers.add_text(
+        (symbol, Vector::Tuple(0.0, 0.0)),
+        0.4,
+        Color::white(),
+        &options.position,
+    )
+}
+
 /// [`Prototypes/ArithmeticCombinatorPrototype`](https://lua-api.factorio.com/latest/prototypes/ArithmeticCombinatorPrototype.html)
 pub type ArithmeticCombinatorPrototype = CombinatorPrototype<ArithmeticCombinatorData>;
 
@@ -115,29 +129,31 @@ impl super::Renderable for ArithmeticCombinatorData {
         render_layers: &mut crate::RenderLayerBuffer,
         image_cache: &mut ImageCache,
     ) -> super::RenderOutput {
-        let res = options.arithmetic_operation.as_ref().and_then(|op| {
-            match op {
-                ArithmeticOperation::Add => self.plus_symbol_sprites.as_ref(),
-                ArithmeticOperation::Subtract => self.minus_symbol_sprites.as_ref(),
-                ArithmeticOperation::Multiply => self.multiply_symbol_sprites.as_ref(),
-                ArithmeticOperation::Divide => self.divide_symbol_sprites.as_ref(),
-                ArithmeticOperation::Modulo => self.modulo_symbol_sprites.as_ref(),
-                ArithmeticOperation::Power => self.power_symbol_sprites.as_ref(),
-                ArithmeticOperation::LeftShift => self.left_shift_symbol_sprites.as_ref(),
-                ArithmeticOperation::RightShift => self.right_shift_symbol_sprites.as_ref(),
-                ArithmeticOperation::BitwiseAnd => self.and_symbol_sprites.as_ref(),
-                ArithmeticOperation::BitwiseOr => self.or_symbol_sprites.as_ref(),
-                ArithmeticOperation::BitwiseXor => self.xor_symbol_sprites.as_ref(),
-            }
-            .and_then(|s| {
-                s.render(
-                    render_layers.scale(),
-                    used_mods,
-                    image_cache,
-                    &options.into(),
-                )
-            })
-        })?;
+        let op = options.arithmetic_operation.as_ref()?;
+        let sprites = match op {
+            ArithmeticOperation::Add => self.plus_symbol_sprites.as_ref(),
+            ArithmeticOperation::Subtract => self.minus_symbol_sprites.as_ref(),
+            ArithmeticOperation::Multiply => self.multiply_symbol_sprites.as_ref(),
+            ArithmeticOperation::Divide => self.divide_symbol_sprites.as_ref(),
+            ArithmeticOperation::Modulo => self.modulo_symbol_sprites.as_ref(),
+            ArithmeticOperation::Power => self.power_symbol_sprites.as_ref(),
+            ArithmeticOperation::LeftShift => self.left_shift_symbol_sprites.as_ref(),
+            ArithmeticOperation::RightShift => self.right_shift_symbol_sprites.as_ref(),
+            ArithmeticOperation::BitwiseAnd => self.and_symbol_sprites.as_ref(),
+            ArithmeticOperation::BitwiseOr => self.or_symbol_sprites.as_ref(),
+            ArithmeticOperation::BitwiseXor => self.xor_symbol_sprites.as_ref(),
+        };
+
+        let Some(sprites) = sprites else {
+            return render_symbol_text(&op.to_string(), options, render_layers);
+        };
+
+        let res = sprites.render(
+            render_layers.scale(),
+            used_mods,
+            image_cache,
+            &options.into(),
+        )?;
 
         render_layers.add_entity(res, &options.position);
 
@@ -175,24 +191,26 @@ impl super::Renderable for DeciderCombinatorData {
         render_layers: &mut crate::RenderLayerBuffer,
         image_cache: &mut ImageCache,
     ) -> super::RenderOutput {
-        let res = options.decider_operation.as_ref().and_then(|op| {
-            match op {
-                Comparator::Equal => self.equal_symbol_sprites.as_ref(),
-                Comparator::Greater => self.greater_symbol_sprites.as_ref(),
-                Comparator::Less => self.less_symbol_sprites.as_ref(),
-                Comparator::NotEqual => self.not_equal_symbol_sprites.as_ref(),
-                Comparator::GreaterOrEqual => self.greater_or_equal_symbol_sprites.as_ref(),
-                Comparator::LessOrEqual => self.less_or_equal_symbol_sprites.as_ref(),
-            }
-            .and_then(|s| {
-                s.render(
-                    render_layers.scale(),
-                    used_mods,
-                    image_cache,
-                    &options.into(),
-                )
-            })
-        })?;
+        let op = options.decider_operation.as_ref()?;
+        let sprites = match op {
+            Comparator::Equal => self.equal_symbol_sprites.as_ref(),
+            Comparator::Greater => self.greater_symbol_sprites.as_ref(),
+            Comparator::Less => self.less_symbol_sprites.as_ref(),
+            Comparator::NotEqual => self.not_equal_symbol_sprites.as_ref(),
+            Comparator::GreaterOrEqual => self.greater_or_equal_symbol_sprites.as_ref(),
+            Comparator::LessOrEqual => self.less_or_equal_symbol_sprites.as_ref(),
+        };
+
+        let Some(sprites) = sprites else {
+            return render_symbol_text(&op.to_string(), options, render_layers);
+        };
+
+        let res = sprites.render(
+            render_layers.scale(),
+            used_mods,
+            image_cache,
+            &options.into(),
+        )?;
 
         render_layers.add_entity(res, &options.position);
 
@@ -90,6 +90,58 @@ impl<T: super::Renderable> Deref for CraftingMachineData<T> {
     }
 }
 
+impl<T: super::Renderable> CraftingMachineData<T> {
+    /// Fluid boxes that exist with the current recipe, input / output boxes can be disabled
+    /// when the recipe doesn't use fluids.
+    fn active_fluid_boxes(&self, options: &super::RenderOpts) -> Vec<&FluidBox> {
+        let Some(fluid_boxes) = &self.fluid_boxes else {
+            return Vec::new();
+        };
+
+        match fluid_boxes {
+            CraftingMachineFluidBoxHell::Array(fbs) => fbs.iter().collect(),
+            CraftingMachineFluidBoxHell::WHY(why) => {
+                let mut inputs = Vec::new();
+                let mut outputs = Vec::new();
+                let mut res = Vec::new();
+                let mut disable = false;
+
+                for e in why.values() {
+                    match e {
+                        CraftingMachineFluidBoxCursedType::FluidBox(fb) => {
+                            match fb.production_type {
+                                FluidBoxProductionType::None
+                                | FluidBoxProductionType::None2
+                                | FluidBoxProductionType::InputOutput => &mut res,
+                                FluidBoxProductionType::Input => &mut inputs,
+                                FluidBoxProductionType::Output => &mut outputs,
+                            }
+                            .push(fb);
+                        }
+                        CraftingMachineFluidBoxCursedType::OffWhenNoFluidRecipe(
+                            no_recipe_disable,
+                        ) => {
+                            disable = *no_recipe_disable;
+                        }
+                    }
+                }
+
+                let (recipe_in, recipe_out) = options.fluid_recipe;
+
+                if recipe_in || !disable {
+                    res.append(&mut inputs);
+                }
+
+                if recipe_out || !disable {
+                    res.append(&mut outputs);
+                }
+
+                res
+            }
+        }
+    }
+}
+
 impl<T: super::Renderable> super::Renderable for CraftingMachineData<T> {
     fn render(
         &self,
@@ -122,53 +174,10 @@ impl<T: super::Renderable> super::Renderable for CraftingMachineData<T> {
 
     fn fluid_box_connections(&self, options: &super::RenderOpts) -> Vec<MapPosition> {
         let mut res = self
-            .fluid_boxes
-            .as_ref()
-            .map(|b| match b {
-                CraftingMachineFluidBoxHell::Array(fbs) => fbs
-                    .iter()
-                    .flat_map(|fb| fb.connection_points(options.direction))
-                    .collect(),
-                CraftingMachineFluidBoxHell::WHY(why) => {
-                    let mut inputs = Vec::new();
-                    let mut outputs = Vec::new();
-                    let mut res = Vec::new();
-                    let mut disable = false;
-
-                    for e in why.values() {
-                        match e {
-                            CraftingMachineFluidBoxCursedType::FluidBox(fb) => {
-                                match fb.production_type {
-                                    FluidBoxProductionType::None
-                                    | FluidBoxProductionType::None2
-                                    | FluidBoxProductionType::InputOutput => &mut res,
-                                    FluidBoxProductionType::Input => &mut inputs,
-                                    FluidBoxProductionType::Output => &mut outputs,
-                                }
-                                .append(&mut fb.connection_points(options.direction));
-                            }
-                            CraftingMachineFluidBoxCursedType::OffWhenNoFluidRecipe(
-                                no_recipe_disable,
-                            ) => {
-                                disable = *no_recipe_disable;
-                            }
-                        }
-                    }
-
-                    let (recipe_in, recipe_out) = options.fluid_recipe;
-
-                    if recipe_in || !disable {
-                        res.append(&mut inputs);
-                    }
-
-                    if recipe_out || !disable {
-                        res.append(&mut outputs);
-                    }
-
-                    res
-                }
-            })
-            .unwrap_or_default();
+            .active_fluid_boxes(options)
+            .into_iter()
+            .flat_map(|fb| fb.connection_points(options.direction))
+            .collect::<Vec<_>>();
 
         res.append(&mut self.child.fluid_box_connections(options));
         res
@@ -177,6 +186,16 @@ impl<T: super::Renderable> super::Renderable for CraftingMachineData<T> {
     fn heat_buffer_connections(&self, options: &super::RenderOpts) -> Vec<MapPosition> {
         self.child.heat_buffer_connections(options)
     }
+
+    fn fluid_box_indicators(
+        &self,
+        options: &super::RenderOpts,
+    ) -> Vec<(MapPosition, PipeConnectionType)> {
+        self.active_fluid_boxes(options)
+            .into_iter()
+            .flat_map(|fb| fb.indicated_connections(options.direction))
+            .collect()
+    }
 }
 
 // TODO: find a better way to work around this abomination of a type
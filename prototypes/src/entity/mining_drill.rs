@@ -103,4 +103,15 @@ impl super::Renderable for MiningDrillData {
     fn heat_buffer_connections(&self, options: &super::RenderOpts) -> Vec<MapPosition> {
         Vec::with_capacity(0)
     }
+
+    fn fluid_box_indicators(
+        &self,
+        options: &super::RenderOpts,
+    ) -> Vec<(MapPosition, PipeConnectionType)> {
+        self.input_fluid_box
+            .iter()
+            .chain(&self.output_fluid_box)
+            .flat_map(|b| b.indicated_connections(options.direction))
+            .collect()
+    }
 }
@@ -247,8 +247,11 @@ impl<T: super::Renderable> super::Renderable for RollingStockData<T> {
             .orientation
             .unwrap_or_else(|| options.direction.to_orientation());
 
+        // the projection is applied by the rotated sprites themselves,
+        // the color mask falls back to the default color of the prototype
         let options = &super::RenderOpts {
-            orientation: Some(orientation.projected_orientation()),
+            orientation: Some(orientation),
+            runtime_tint: options.runtime_tint.or(self.color),
             ..options.clone()
         };
 
@@ -282,7 +285,7 @@ impl<T: super::Renderable> super::Renderable for RollingStockData<T> {
             }
 
             let other_wheel_opts = RotatedSpriteRenderOpts {
-                orientation: (orientation.projected_orientation() + 0.5).rem(1.0),
+                orientation: (orientation + 0.5).rem(1.0),
                 runtime_tint: options.runtime_tint,
             };
 
@@ -406,10 +409,12 @@ impl super::Renderable for ArtilleryWagonData {
                     Vector::new(0.0, 0.0)
                 } else {
                     let len_f = shifts.len() as f64;
+                    // the shiftings match the frames of the projected cannon base
                     let idx = (len_f
                         * options
                             .orientation
-                            .unwrap_or_else(|| options.direction.to_orientation()))
+                            .unwrap_or_else(|| options.direction.to_orientation())
+                            .projected_orientation())
                     .floor();
 
                     let idx = if idx < 0.0 {
@@ -15,6 +15,8 @@ pub struct ModulePrototypeData {
     pub tier: u32,
     pub effect: Effect,
 
+    pub art_style: Option<String>,
+
     #[serde(default = "helper::bool_true", skip_serializing_if = "Clone::clone")]
     pub requires_beacon_alt_mode: bool,
 
@@ -28,6 +28,8 @@ pub mod fluid;
 pub mod item;
 pub mod recipe;
 pub mod signal;
+pub mod svg;
+pub mod text;
 pub mod tile;
 pub mod utility_sprites;
 
@@ -845,18 +847,67 @@ impl DataUtil {
             .render(render_opts, used_mods, render_layers, image_cache)
     }
 
-    pub fn render_tile(
+    /// Renders tiles (by their top left position) and the transitions between them and to empty positions.
+    ///
+    /// Returns the number of drawn sprites.
+    pub fn render_tiles(
         &self,
-        tile_name: &str,
-        position: &MapPosition,
+        tiles: &HashMap<(i32, i32), &str>,
         used_mods: &UsedMods,
         render_layers: &mut crate::RenderLayerBuffer,
         image_cache: &mut ImageCache,
-    ) -> Option<()> {
-        self.raw
-            .tile
-            .get(tile_name)
-            .and_then(|t| t.render(position, used_mods, render_layers, image_cache))
+    ) -> usize {
+        let get = |position: (i32, i32)| {
+            tiles
+                .get(&position)
+                .and_then(|name| self.raw.tile.get(*name))
+        };
+
+        let mut count = 0;
+        for (&position, name) in tiles {
+            let Some(tile) = self.raw.tile.get(*name) else {
+                continue;
+            };
+
+            let is_same = |other| tiles.get(&other) == Some(name);
+            if tile
+                .render(position, is_same, used_mods, render_layers, image_cache)
+                .is_some()
+            {
+                count += 1;
+            }
+        }
+
+        // every position next to a tile of a higher layer gets the transitions of that tile,
+        // lower layers are drawn first so that higher ones end up on top
+        let mut transitions = std::collections::BTreeSet::new();
+        for &(x, y) in tiles.keys() {
+            let Some(tile) = get((x, y)) else {
+                continue;
+            };
+
+            for (dx, dy) in tile::SIDES.iter().chain(&tile::CORNERS) {
+                let target = (x + dx, y + dy);
+                let below = get(target)
+                    .is_none_or(|other| other.layer < tile.layer && !other.merges_with(&tile.name));
+
+                if below {
+                    transitions.insert((tile.layer, tile.name.as_str(), target));
+                }
+            }
+        }
+
+        for (_, name, position) in transitions {
+            let Some(tile) = self.raw.tile.get(name) else {
+                continue;
+            };
+
+            let is_this = |other| get(other).is_some_and(|other| other.merges_with(name));
+            count +=
+                tile.render_transitions(position, is_this, used_mods, render_layers, image_cache);
+        }
+
+        count
     }
 
     pub fn get_item_icon(
@@ -879,6 +930,20 @@ impl DataUtil {
         self.raw.fluid.get_icon(name, scale, used_mods, image_cache)
     }
 
+    pub fn get_tile_icon(
+        &self,
+        name: &str,
+        scale: f64,
+        used_mods: &mod_util::UsedMods,
+        image_cache: &mut types::ImageCache,
+    ) -> Option<types::GraphicsOutput> {
+        self.raw
+            .tile
+            .get(name)
+            .and_then(|t| t.icon.as_ref())
+            .and_then(|icon| icon.render(scale, used_mods, image_cache, &()))
+    }
+
     pub fn get_signal_icon(
         &self,
         name: &str,
@@ -909,6 +974,67 @@ impl DataUtil {
         )
     }
 
+    /// Default pickup & drop vectors of an inserter (relative to the inserter facing north).
+    ///
+    /// Returns `None` if the entity is no inserter or its prototype disables the alt-mode arrow.
+    #[must_use]
+    pub fn get_inserter_vectors(&self, name: &str) -> Option<(Vector, Vector)> {
+        let inserter = self.raw.entity.inserter.get(name)?;
+
+        if !inserter.draw_inserter_arrow {
+            return None;
+        }
+
+        Some((inserter.pickup_position, inserter.insert_position))
+    }
+
+    /// Art style & tier of a module item, `None` if the item is no module.
+    #[must_use]
+    pub fn get_module(&self, name: &str) -> Option<types::InsertedModule> {
+        let module = self.raw.item.module.get(name)?;
+
+        Some(types::InsertedModule {
+            art_style: module.art_style.clone(),
+            tier: module.tier,
+        })
+    }
+
+    /// Module slots & icon layout of an entity.
+    ///
+    /// Returns `None` if the entity has no module slots or doesn't show the module icons in alt-mode.
+    #[must_use]
+    pub fn get_module_specification(&self, name: &str) -> Option<&types::ModuleSpecification> {
+        let entity = &self.raw.entity;
+        let spec = match self.get_type(name)? {
+            entity::Type::AssemblingMachine => entity
+                .assembling_machine
+                .get(name)?
+                .module_specification
+                .as_ref(),
+            entity::Type::Furnace => entity.furnace.get(name)?.module_specification.as_ref(),
+            entity::Type::RocketSilo => entity.rocket_silo.get(name)?.module_specification.as_ref(),
+            entity::Type::Lab => entity.lab.get(name)?.module_specification.as_ref(),
+            entity::Type::MiningDrill => {
+                entity.mining_drill.get(name)?.module_specification.as_ref()
+            }
+            entity::Type::Beacon => {
+                let beacon = entity.beacon.get(name)?;
+                if beacon
+                    .graphics_set
+                    .as_ref()
+                    .is_some_and(|g| g.module_icons_suppressed)
+                {
+                    return None;
+                }
+
+                Some(&beacon.module_specification)
+            }
+            _ => None,
+        }?;
+
+        (spec.module_slots > 0).then_some(spec)
+    }
+
     #[must_use]
     pub fn recipe_has_fluid(&self, name: &str) -> (bool, bool) {
         self.raw.recipe.uses_fluid(name)
@@ -946,11 +1072,12 @@ pub enum InternalRenderLayer {
     DirectionOverlay,
     IconOutline,
     IconOverlay,
+    Text,
 }
 
 impl InternalRenderLayer {
     #[must_use]
-    pub const fn all() -> [Self; 18] {
+    pub const fn all() -> [Self; 19] {
         [
             Self::Background,
             Self::Ground,
@@ -970,6 +1097,7 @@ impl InternalRenderLayer {
             Self::DirectionOverlay,
             Self::IconOutline,
             Self::IconOverlay,
+            Self::Text,
         ]
     }
 }
@@ -1039,6 +1167,8 @@ impl std::fmt::Display for TargetSize {
 pub struct RenderLayerBuffer {
     target_size: TargetSize,
     layers: HashMap<InternalRenderLayer, image::DynamicImage>,
+    font: Option<text::Font>,
+    svg: Option<svg::SvgRecorder>,
 
     wire_connection_points: HashMap<u64, GenericWireConnectionPoint>,
 }
@@ -1052,10 +1182,25 @@ impl RenderLayerBuffer {
         Self {
             target_size,
             layers: HashMap::new(),
+            font: None,
+            svg: None,
             wire_connection_points: HashMap::new(),
         }
     }
 
+    /// Additionally records all draw calls so that they can be written as SVG with [`Self::to_svg`].
+    #[must_use]
+    pub fn with_svg(mut self) -> Self {
+        self.svg = Some(svg::SvgRecorder::default());
+        self
+    }
+
+    /// The recorded draw calls as SVG document, `None` if the buffer was not created [`Self::with_svg`].
+    #[must_use]
+    pub fn to_svg(&self) -> Option<String> {
+        self.svg.as_ref().map(|svg| svg.write(&self.target_size))
+    }
+
     fn get_layer(&mut self, layer: InternalRenderLayer) -> &mut image::DynamicImage {
         self.layers.entry(layer).or_insert_with(|| {
             image::DynamicImage::new_rgba8(self.target_size.width, self.target_size.height)
@@ -1072,10 +1217,51 @@ impl RenderLayerBuffer {
             .target_size
             .get_pixel_pos(img.dimensions(), &shift, position);
 
+        if let Some(svg) = &mut self.svg {
+            let (tl_x, tl_y) = self.target_size.top_left.as_tuple();
+            let tile_res = self.target_size.tile_res;
+
+            svg.add_image(
+                layer,
+                &img,
+                (x as f64 / tile_res + tl_x, y as f64 / tile_res + tl_y),
+                tile_res,
+            );
+        }
+
         let layer = self.get_layer(layer);
         imageops::overlay(layer, &img, x, y);
     }
 
+    /// Sets the font that is used by [`Self::add_text`].
+    pub fn set_font(&mut self, font: text::Font) {
+        self.font = Some(font);
+    }
+
+    #[must_use]
+    pub const fn font(&self) -> Option<&text::Font> {
+        self.font.as_ref()
+    }
+
+    /// Draws a line of text centered on `position` + `shift` into the outlined text layer.
+    ///
+    /// `size` is the text height in tiles, returns `None` if nothing was drawn (e.g. no font is set).
+    pub fn add_text(
+        &mut self,
+        (text, shift): (&str, Vector),
+        size: f64,
+        color: Color,
+        position: &MapPosition,
+    ) -> Option<()> {
+        let img = self
+            .font
+            .as_ref()?
+            .render(text, size * self.target_size.tile_res, color)?;
+        self.add((img, shift), position, InternalRenderLayer::Text);
+
+        Some(())
+    }
+
     pub fn add_entity(&mut self, input: (image::DynamicImage, Vector), position: &MapPosition) {
         self.add(input, position, InternalRenderLayer::Entity);
     }
@@ -1241,6 +1427,11 @@ impl RenderLayerBuffer {
                 let end = *t_pos + &MapPosition::from(*t_offset);
                 let length = start.distance_to(&end);
 
+                // the SVG output gets real curves instead of the stretched sprite
+                if let Some(svg) = &mut self.svg {
+                    svg.add_wire(usize::from(i), start.clone(), end.clone());
+                }
+
                 let mut orientation = start.rad_orientation_to(&end);
                 if orientation > std::f64::consts::FRAC_PI_2 {
                     orientation -= std::f64::consts::PI;
@@ -1290,11 +1481,12 @@ impl RenderLayerBuffer {
                     image::Rgba([0, 0, 0, 0]),
                 );
 
-                self.add(
-                    (rotated.into(), Vector::default()),
+                let (px, py) = self.target_size.get_pixel_pos(
+                    rotated.dimensions(),
+                    &Vector::default(),
                     &start.center_to(&end),
-                    InternalRenderLayer::Wire,
                 );
+                imageops::overlay(self.get_layer(InternalRenderLayer::Wire), &rotated, px, py);
             }
         }
     }
@@ -1323,15 +1515,29 @@ impl RenderLayerBuffer {
 
         self.layers
             .insert(InternalRenderLayer::Background, background.into());
+
+        if let Some(svg) = &mut self.svg {
+            svg.add_background();
+        }
     }
 
     #[must_use]
     pub fn combine(&mut self) -> image::DynamicImage {
         'sdf_outline: {
-            if let Some(icons) = self.layers.get(&InternalRenderLayer::IconOverlay) {
-                let (width, height) = icons.dimensions();
+            // icons & text share the outline
+            let overlays = [InternalRenderLayer::IconOverlay, InternalRenderLayer::Text]
+                .iter()
+                .filter_map(|layer| self.layers.get(layer))
+                .collect::<Vec<_>>();
+
+            if let Some(first) = overlays.first() {
+                let (width, height) = first.dimensions();
                 let mask = image::ImageBuffer::from_fn(width, height, |x, y| {
-                    let alpha = icons.get_pixel(x, y).0[3];
+                    let alpha = overlays
+                        .iter()
+                        .map(|img| img.get_pixel(x, y).0[3])
+                        .max()
+                        .unwrap_or_default();
                     image::Luma([alpha])
                 });
 
@@ -0,0 +1,325 @@
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    hash::{DefaultHasher, Hash, Hasher},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use image::{codecs::png::PngEncoder, DynamicImage, ImageEncoder};
+
+use types::MapPosition;
+
+use crate::{InternalRenderLayer, TargetSize};
+
+/// Stroke colors of the copper, red & green wires.
+const WIRE_COLORS: [(&str, &str); 3] = [
+    ("copper", "#cf7e4a"),
+    ("red", "#c83a27"),
+    ("green", "#4d9e3d"),
+];
+
+/// Wire thickness in tiles.
+const WIRE_WIDTH: f64 = 0.05;
+
+/// How far a wire sags in the middle relative to its length.
+const WIRE_SAG: f64 = 0.1;
+
+#[derive(Debug, Clone)]
+struct Sprite {
+    /// Decoded image to tell sprites with the same hash apart.
+    image: DynamicImage,
+    data: String,
+    width: f64,
+    height: f64,
+}
+
+#[derive(Debug, Clone)]
+enum Element {
+    /// Index of the sprite and the position of its top left corner in tiles.
+    Image { sprite: usize, x: f64, y: f64 },
+    Wire {
+        color: usize,
+        start: MapPosition,
+        end: MapPosition,
+    },
+}
+
+/// Records the draw calls of a [`RenderLayerBuffer`](crate::RenderLayerBuffer) to write them as SVG.
+///
+/// Every sprite is embedded once as PNG data URI and referenced by all draw calls that use it.
+/// The draw calls are grouped by their [`InternalRenderLayer`] so that single layers can be
+/// hidden with CSS (e.g. `#wire { display: none; }`).
+#[derive(Debug, Clone, Default)]
+pub struct SvgRecorder {
+    sprites: Vec<Sprite>,
+
+    /// Indices of the sprites by the hash of their pixels.
+    sprite_ids: HashMap<u64, Vec<usize>>,
+    elements: HashMap<InternalRenderLayer, Vec<Element>>,
+    background: bool,
+}
+
+impl SvgRecorder {
+    /// Records a sprite with its top left corner at `(x, y)` tiles, `tile_res` is the sprite resolution in px/tile.
+    pub fn add_image(
+        &mut self,
+        layer: InternalRenderLayer,
+        img: &DynamicImage,
+        (x, y): (f64, f64),
+        tile_res: f64,
+    ) {
+        if img.width() == 0 || img.height() == 0 {
+            return;
+        }
+
+        let mut hasher = DefaultHasher::new();
+        (img.width(), img.height()).hash(&mut hasher);
+        img.as_bytes().hash(&mut hasher);
+        let key = hasher.finish();
+
+        let width = f64::from(img.width()) / tile_res;
+        let existing = self.sprite_ids.get(&key).and_then(|ids| {
+            ids.iter().copied().find(|&id| {
+                let sprite = &self.sprites[id];
+                (sprite.width - width).abs() < f64::EPSILON && sprite.image == *img
+            })
+        });
+
+        let sprite = if let Some(sprite) = existing {
+            sprite
+        } else {
+            let Some(data) = encode_data_uri(img) else {
+                return;
+            };
+
+            self.sprites.push(Sprite {
+                image: img.clone(),
+                data,
+                width,
+                height: f64::from(img.height()) / tile_res,
+            });
+            self.sprite_ids
+                .entry(key)
+                .or_default()
+                .push(self.sprites.len() - 1);
+            self.sprites.len() - 1
+        };
+
+        self.elements
+            .entry(layer)
+            .or_default()
+            .push(Element::Image { sprite, x, y });
+    }
+
+    /// Records a wire between two positions, `color` is 0 for copper, 1 for red and 2 for green.
+    pub fn add_wire(&mut self, color: usize, start: MapPosition, end: MapPosition) {
+        self.elements
+            .entry(InternalRenderLayer::Wire)
+            .or_default()
+            .push(Element::Wire { color, start, end });
+    }
+
+    /// Adds the checkerboard background.
+    pub const fn add_background(&mut self) {
+        self.background = true;
+    }
+
+    /// Writes the recorded draw calls as SVG document in tile coordinates.
+    #[must_use]
+    pub fn write(&self, target: &TargetSize) -> String {
+        let (tl_x, tl_y) = target.top_left.as_tuple();
+        let width = f64::from(target.width) / target.tile_res;
+        let height = f64::from(target.height) / target.tile_res;
+
+        let mut svg = String::new();
+
+        // writing into a string can't fail
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="{tl_x} {tl_y} {width} {height}">"#,
+            target.width, target.height
+        );
+
+        svg.push_str("<defs>\n");
+        svg.push_str(concat!(
+            r##"<pattern id="checkerboard" width="2" height="2" patternUnits="userSpaceOnUse">"##,
+            r##"<rect width="2" height="2" fill="#313131"/>"##,
+            r##"<rect width="1" height="1" fill="#1b1b1b"/>"##,
+            r##"<rect x="1" y="1" width="1" height="1" fill="#1b1b1b"/>"##,
+            "</pattern>\n",
+        ));
+
+        // icons & text are outlined like in the raster output
+        svg.push_str(concat!(
+            r#"<filter id="outline" x="-10%" y="-10%" width="120%" height="120%">"#,
+            r#"<feMorphology in="SourceAlpha" operator="dilate" radius="0.08" result="dilated"/>"#,
+            r#"<feGaussianBlur in="dilated" stdDeviation="0.04" result="blurred"/>"#,
+            r#"<feFlood flood-color="black"/>"#,
+            r#"<feComposite in2="blurred" operator="in" result="shadow"/>"#,
+            r#"<feMerge><feMergeNode in="shadow"/><feMergeNode in="SourceGraphic"/></feMerge>"#,
+            "</filter>\n",
+        ));
+
+        for (idx, sprite) in self.sprites.iter().enumerate() {
+            let _ = writeln!(
+                svg,
+                r#"<image id="s{idx}" width="{:.4}" height="{:.4}" preserveAspectRatio="none" href="{}"/>"#,
+                sprite.width, sprite.height, sprite.data
+            );
+        }
+        svg.push_str("</defs>\n");
+
+        for layer in InternalRenderLayer::all() {
+            let elements = self.elements.get(&layer).map_or(&[][..], Vec::as_slice);
+
+            let background = layer == InternalRenderLayer::Background && self.background;
+            if elements.is_empty() && !background {
+                continue;
+            }
+
+            let filter = match layer {
+                InternalRenderLayer::IconOverlay | InternalRenderLayer::Text => {
+                    r#" filter="url(#outline)""#
+                }
+                _ => "",
+            };
+
+            let _ = writeln!(svg, r#"<g id="{}"{filter}>"#, layer_id(layer));
+
+            if background {
+                let _ = writeln!(
+                    svg,
+                    r#"<rect x="{tl_x}" y="{tl_y}" width="{width}" height="{height}" fill="url(#checkerboard)"/>"#
+                );
+            }
+
+            for element in elements {
+                match element {
+                    Element::Image { sprite, x, y } => {
+                        let _ =
+                            writeln!(svg, r##"<use href="#s{sprite}" x="{x:.4}" y="{y:.4}"/>"##);
+                    }
+                    Element::Wire { color, start, end } => {
+                        let (name, stroke) = WIRE_COLORS[color % WIRE_COLORS.len()];
+                        let (start_x, start_y) = start.as_tuple();
+                        let (end_x, end_y) = end.as_tuple();
+                        let sag = start.distance_to(end) * WIRE_SAG;
+
+                        let _ = writeln!(
+                            svg,
+                            r#"<path class="{name}" d="M{start_x:.4} {start_y:.4} Q{:.4} {:.4} {end_x:.4} {end_y:.4}" fill="none" stroke="{stroke}" stroke-width="{WIRE_WIDTH}" stroke-linecap="round"/>"#,
+                            (start_x + end_x) / 2.0,
+                            sag.mul_add(2.0, (start_y + end_y) / 2.0),
+                        );
+                    }
+                }
+            }
+
+            svg.push_str("</g>\n");
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+fn encode_data_uri(img: &DynamicImage) -> Option<String> {
+    let mut png = Vec::new();
+    PngEncoder::new(&mut png)
+        .write_image(img.as_bytes(), img.width(), img.height(), img.color())
+        .ok()?;
+
+    Some(format!("data:image/png;base64,{}", STANDARD.encode(png)))
+}
+
+/// Id of the group of a layer, can be used to style / toggle the layer with CSS.
+const fn layer_id(layer: InternalRenderLayer) -> &'static str {
+    match layer {
+        InternalRenderLayer::Background => "background",
+        InternalRenderLayer::Ground => "ground",
+        InternalRenderLayer::GroundPatch => "ground-patch",
+        InternalRenderLayer::RailStonePathBackground => "rail-stone-path-background",
+        InternalRenderLayer::RailStonePath => "rail-stone-path",
+        InternalRenderLayer::RailTies => "rail-ties",
+        InternalRenderLayer::RailBackplate => "rail-backplate",
+        InternalRenderLayer::RailMetal => "rail-metal",
+        InternalRenderLayer::Shadow => "shadow",
+        InternalRenderLayer::Entity => "entity",
+        InternalRenderLayer::EntityHigh => "entity-high",
+        InternalRenderLayer::EntityHigher => "entity-higher",
+        InternalRenderLayer::InserterHand => "inserter-hand",
+        InternalRenderLayer::AboveEntity => "above-entity",
+        InternalRenderLayer::Wire => "wire",
+        InternalRenderLayer::DirectionOverlay => "direction-overlay",
+        InternalRenderLayer::IconOutline => "icon-outline",
+        InternalRenderLayer::IconOverlay => "icon-overlay",
+        InternalRenderLayer::Text => "text",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    #[test]
+    fn deduplicates_sprites() {
+        let target = TargetSize::new(
+            64,
+            64,
+            1.0,
+            MapPosition::Tuple(0.0, 0.0),
+            MapPosition::Tuple(2.0, 2.0),
+        );
+
+        let img = DynamicImage::new_rgba8(32, 32);
+        let mut recorder = SvgRecorder::default();
+        recorder.add_image(InternalRenderLayer::Entity, &img, (0.0, 0.0), 32.0);
+        recorder.add_image(InternalRenderLayer::Entity, &img, (1.0, 1.0), 32.0);
+        recorder.add_wire(
+            1,
+            MapPosition::Tuple(0.5, 0.5),
+            MapPosition::Tuple(1.5, 0.5),
+        );
+
+        let svg = recorder.write(&target);
+        assert_eq!(svg.matches("<image ").count(), 1);
+        assert_eq!(svg.matches("<use ").count(), 2);
+        assert!(svg.contains(r#"<g id="entity">"#));
+        assert!(svg.contains(r#"<path class="red""#));
+        assert!(!svg.contains(r#"<g id="background">"#));
+    }
+
+    #[test]
+    fn keeps_different_sprites_apart() {
+        let target = TargetSize::new(
+            64,
+            64,
+            1.0,
+            MapPosition::Tuple(0.0, 0.0),
+            MapPosition::Tuple(2.0, 2.0),
+        );
+
+        let empty = DynamicImage::new_rgba8(32, 32);
+        let mut filled = image::RgbaImage::new(32, 32);
+        filled.put_pixel(0, 0, image::Rgba([255, 0, 0, 255]));
+
+        let mut recorder = SvgRecorder::default();
+        recorder.add_image(InternalRenderLayer::Entity, &empty, (0.0, 0.0), 32.0);
+        recorder.add_image(
+            InternalRenderLayer::Shadow,
+            &filled.into(),
+            (1.0, 1.0),
+            32.0,
+        );
+
+        let svg = recorder.write(&target);
+        assert_eq!(svg.matches("<image ").count(), 2);
+
+        // layers are written in render order
+        let shadow = svg.find(r#"<g id="shadow">"#).unwrap();
+        let entity = svg.find(r#"<g id="entity">"#).unwrap();
+        assert!(shadow < entity);
+    }
+}
@@ -0,0 +1,173 @@
+use image::{DynamicImage, Rgba, RgbaImage};
+use rusttype::{point, PositionedGlyph, Scale};
+
+use mod_util::UsedMods;
+use types::Color;
+
+/// Fallback font in case the `core` mod fonts are not available.
+const BUNDLED_FONT: &[u8] = include_bytes!("../fonts/DejaVuSansCondensed-Bold.ttf");
+
+/// Font used for station names & other labels.
+#[derive(Debug, Clone)]
+pub struct Font {
+    font: rusttype::Font<'static>,
+}
+
+impl Font {
+    /// The font the game uses for most of its labels.
+    pub const CORE_FONT: &'static str = "fonts/TitilliumWeb-SemiBold.ttf";
+
+    /// Loads the font of the `core` mod, falls back to the bundled font if that is not possible.
+    #[must_use]
+    pub fn load(used_mods: &UsedMods) -> Option<Self> {
+        Self::core(used_mods).or_else(Self::bundled)
+    }
+
+    /// Loads [`Self::CORE_FONT`] from the `core` mod.
+    #[must_use]
+    pub fn core(used_mods: &UsedMods) -> Option<Self> {
+        let data = used_mods.get("core")?.get_file(Self::CORE_FONT).ok()?;
+        let font = rusttype::Font::try_from_vec(data)?;
+
+        Some(Self { font })
+    }
+
+    /// The font that is compiled into the binary.
+    #[must_use]
+    pub fn bundled() -> Option<Self> {
+        let font = rusttype::Font::try_from_bytes(BUNDLED_FONT)?;
+
+        Some(Self { font })
+    }
+
+    fn layout(&self, text: &str, size: f64) -> (Vec<PositionedGlyph<'static>>, u32, u32) {
+        let scale = Scale::uniform(size as f32);
+        let metrics = self.font.v_metrics(scale);
+        let glyphs = self
+            .font
+            .layout(text, scale, point(0.0, metrics.ascent))
+            .collect::<Vec<_>>();
+
+        let width = glyphs
+            .last()
+            .map_or(0.0, |g| {
+                g.position().x + g.unpositioned().h_metrics().advance_width
+            })
+            .ceil() as u32;
+        let height = (metrics.ascent - metrics.descent).ceil() as u32;
+
+        (glyphs, width, height)
+    }
+
+    /// Width of a single line of text with a height of `size` pixels.
+    #[must_use]
+    pub fn width(&self, text: &str, size: f64) -> u32 {
+        self.layout(text, size).1
+    }
+
+    /// Shortens `text` with an ellipsis so that it is at most `max_width` pixels wide.
+    #[must_use]
+    pub fn fit(&self, text: &str, size: f64, max_width: u32) -> String {
+        let (glyphs, width, _) = self.layout(text, size);
+        if width <= max_width {
+            return text.to_owned();
+        }
+
+        let ellipsis = self.width("…", size);
+        if ellipsis > max_width {
+            return String::new();
+        }
+
+        // the layout has one glyph per char, so the text is cut before the first glyph that doesn't fit
+        let available = (max_width - ellipsis) as f32;
+        let mut count = glyphs
+            .iter()
+            .take_while(|g| {
+                g.position().x + g.unpositioned().h_metrics().advance_width <= available
+            })
+            .count();
+
+        // kerning with the ellipsis & rounding can still push it over the limit by a little
+        loop {
+            let shortened = format!(
+                "{}…",
+                text.chars().take(count).collect::<String>().trim_end()
+            );
+            if count == 0 || self.width(&shortened, size) <= max_width {
+                return shortened;
+            }
+
+            count -= 1;
+        }
+    }
+
+    /// Renders a single line of text with a height of `size` pixels.
+    #[must_use]
+    pub fn render(&self, text: &str, size: f64, color: Color) -> Option<DynamicImage> {
+        let (glyphs, width, height) = self.layout(text, size);
+
+        if width == 0 || height == 0 {
+            return None;
+        }
+
+        let [red, green, blue, alpha] = color.to_rgba();
+        let [red, green, blue] = [red, green, blue].map(|c| (c * 255.0).round() as u8);
+
+        let mut img = RgbaImage::new(width, height);
+        for glyph in &glyphs {
+            let Some(bb) = glyph.pixel_bounding_box() else {
+                continue;
+            };
+
+            glyph.draw(|gx, gy, coverage| {
+                let (Ok(px), Ok(py)) = (
+                    u32::try_from(i64::from(gx) + i64::from(bb.min.x)),
+                    u32::try_from(i64::from(gy) + i64::from(bb.min.y)),
+                ) else {
+                    return;
+                };
+
+                if px >= width || py >= height {
+                    return;
+                }
+
+                // overlapping glyphs keep the higher coverage
+                let pixel = img.get_pixel_mut(px, py);
+                let coverage = (f64::from(coverage) * alpha * 255.0).round() as u8;
+                if coverage > pixel.0[3] {
+                    *pixel = Rgba([red, green, blue, coverage]);
+                }
+            });
+        }
+
+        Some(img.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    #[test]
+    fn bundled_font_renders() {
+        let font = Font::bundled().unwrap();
+        let img = font.render("Iron Plate", 32.0, Color::white()).unwrap();
+
+        assert_eq!(img.width(), font.width("Iron Plate", 32.0));
+        assert!(img.height() >= 32);
+    }
+
+    #[test]
+    fn fit_shortens_with_ellipsis() {
+        let font = Font::bundled().unwrap();
+        let text = "A very long station name that does not fit";
+
+        let fitted = font.fit(text, 16.0, 100);
+        assert!(fitted.ends_with('…'));
+        assert!(font.width(&fitted, 16.0) <= 100);
+
+        assert_eq!(font.fit("Short", 16.0, 100), "Short");
+        assert_eq!(font.fit(text, 16.0, 1), "");
+    }
+}
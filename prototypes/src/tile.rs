@@ -5,46 +5,236 @@ use serde_with::skip_serializing_none;
 use serde_helper as helper;
 use types::{
     CollisionMask, Color, FactorioArray, Icon, ImageCache, MapPosition, PlaceableBy,
-    RenderableGraphics, SimpleGraphicsRenderOpts, TileID, TileSprite, TileSpriteWithProbability,
+    SimpleGraphicsRenderOpts, TileID, TileSprite, TileSpriteWithProbability, TileTransitionSprite,
 };
 
 use crate::InternalRenderLayer;
 
+/// Offsets of the sides of a tile: north, east, south, west.
+pub const SIDES: [(i32, i32); 4] = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+
+/// Offsets of the corners of a tile: north east, south east, south west, north west.
+pub const CORNERS: [(i32, i32); 4] = [(1, -1), (1, 1), (-1, 1), (-1, -1)];
+
+/// Deterministic pseudo random number for a tile position so that every render picks the same variations.
+const fn position_hash((x, y): (i32, i32), salt: u32) -> u32 {
+    let mut hash = (x as u32).wrapping_mul(0x9E37_79B9)
+        ^ (y as u32).wrapping_mul(0x85EB_CA6B)
+        ^ salt.wrapping_mul(0xC2B2_AE35);
+
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x7FEB_352D);
+    hash ^= hash >> 15;
+    hash = hash.wrapping_mul(0x846C_A68B);
+    hash ^ (hash >> 16)
+}
+
+/// Deterministic pseudo random value in `[0, 1)` for a tile position.
+fn position_roll(position: (i32, i32), salt: u32) -> f64 {
+    f64::from(position_hash(position, salt)) / (f64::from(u32::MAX) + 1.0)
+}
+
+fn pick_variation(variant: &TileSpriteWithProbability, position: (i32, i32), salt: u32) -> u32 {
+    let total = variant.weights.iter().sum::<f64>();
+    if variant.weights.is_empty() || total <= 0.0 {
+        return position_hash(position, salt) % variant.count.max(1);
+    }
+
+    let mut roll = position_roll(position, salt) * total;
+    for (idx, weight) in (0..).zip(&variant.weights) {
+        if roll < *weight {
+            return idx;
+        }
+
+        roll -= weight;
+    }
+
+    0
+}
+
 /// [`Prototypes/TilePrototype`](https://lua-api.factorio.com/latest/prototypes/TilePrototype.html)
 pub type TilePrototype = crate::BasePrototype<TilePrototypeData>;
 
 impl TilePrototype {
+    fn render_opts(&self) -> SimpleGraphicsRenderOpts {
+        SimpleGraphicsRenderOpts {
+            runtime_tint: Some(self.tint),
+        }
+    }
+
+    /// Whether this tile blends into `other` without a transition.
+    #[must_use]
+    pub fn merges_with(&self, other: &str) -> bool {
+        self.name == other || self.transition_merges_with_tile.as_deref() == Some(other)
+    }
+
+    /// Renders the tile with its top left corner at `position`.
+    ///
+    /// The variation is picked by the position. `is_same` tells if another position is covered by
+    /// the same tile, variations that span multiple tiles are only used if all of them are covered.
     pub fn render(
         &self,
-        position: &MapPosition,
+        position: (i32, i32),
+        is_same: impl Fn((i32, i32)) -> bool,
         used_mods: &UsedMods,
         render_layers: &mut crate::RenderLayerBuffer,
         image_cache: &mut ImageCache,
     ) -> Option<()> {
-        let opts = SimpleGraphicsRenderOpts {
-            runtime_tint: Some(self.tint),
-        };
+        let scale = render_layers.scale();
+        let opts = self.render_opts();
+
+        let res = if let Some(mb) = &self.variants.material_background {
+            mb.render_variation(
+                scale,
+                used_mods,
+                image_cache,
+                &opts,
+                position_hash(position, 0),
+            )
+        } else {
+            let (variant, variation, piece) = self.main_variation(position, is_same)?;
+            variant.render_variation(scale, used_mods, image_cache, &opts, variation, piece)
+        }?;
+
+        render_layers.add(res, &tile_center(position), InternalRenderLayer::Ground);
+        Some(())
+    }
 
+    /// Size (1 side) of the biggest variant in tiles, the variant of a tile depends on the tiles up to this far away.
+    #[must_use]
+    pub fn max_variant_size(&self) -> u32 {
         self.variants
-            .material_background
-            .as_ref()
-            .and_then(|mb| mb.render(render_layers.scale(), used_mods, image_cache, &opts))
-            .or_else(|| {
-                let tile = self.variants.main.iter().find(|t| t.size == 1)?;
-
-                tile.render(
-                    render_layers.scale(),
+            .main
+            .iter()
+            .map(|variant| variant.size.max(1))
+            .max()
+            .unwrap_or(1)
+    }
+
+    /// Picks the biggest variant that fits at `position` and passes its probability roll.
+    fn main_variation(
+        &self,
+        position: (i32, i32),
+        is_same: impl Fn((i32, i32)) -> bool,
+    ) -> Option<(&TileSpriteWithProbability, u32, (u32, u32))> {
+        let mut variants = self.variants.main.iter().collect::<Vec<_>>();
+        variants.sort_by_key(|v| std::cmp::Reverse(v.size));
+
+        for variant in variants {
+            let size = variant.size.max(1);
+            let Ok(tiles) = i32::try_from(size) else {
+                continue;
+            };
+
+            // bigger variations are aligned to a grid of their size
+            let origin = (
+                position.0.div_euclid(tiles) * tiles,
+                position.1.div_euclid(tiles) * tiles,
+            );
+
+            if size > 1 {
+                let covered = (0..tiles)
+                    .all(|dy| (0..tiles).all(|dx| is_same((origin.0 + dx, origin.1 + dy))));
+
+                if !covered || position_roll(origin, size) >= variant.probability {
+                    continue;
+                }
+            }
+
+            let piece = (
+                (position.0 - origin.0) as u32,
+                (position.1 - origin.1) as u32,
+            );
+
+            return Some((variant, pick_variation(variant, origin, size + 1), piece));
+        }
+
+        None
+    }
+
+    /// Draws the transitions of this tile onto a position of a lower layer tile (or an empty position).
+    ///
+    /// `is_this` tells if another position is covered by this tile.
+    /// Returns the number of drawn transition sprites.
+    pub fn render_transitions(
+        &self,
+        position: (i32, i32),
+        is_this: impl Fn((i32, i32)) -> bool,
+        used_mods: &UsedMods,
+        render_layers: &mut crate::RenderLayerBuffer,
+        image_cache: &mut ImageCache,
+    ) -> usize {
+        let sides = SIDES.map(|(dx, dy)| is_this((position.0 + dx, position.1 + dy)));
+        let corners = CORNERS.map(|(dx, dy)| is_this((position.0 + dx, position.1 + dy)));
+        let variants = &self.variants;
+
+        let mut pieces = Vec::new();
+        match sides.iter().filter(|s| **s).count() {
+            4 => pieces.push((variants.o_transition.as_ref(), 0)),
+            3 => {
+                // the u transition is oriented by its open side
+                let open = (0..4u32)
+                    .zip(sides)
+                    .find_map(|(idx, s)| (!s).then_some(idx));
+                pieces.push((variants.u_transition.as_ref(), open.unwrap_or_default()));
+            }
+            _ => {
+                // corner `idx` lies between side `idx` and side `idx + 1`
+                let mut covered = [false; 4];
+                for idx in 0..4 {
+                    let next = (idx + 1) % 4;
+                    if sides[idx] && sides[next] {
+                        pieces.push((variants.inner_corner.as_ref(), idx as u32));
+                        covered[idx] = true;
+                        covered[next] = true;
+                    }
+                }
+
+                for idx in 0..4 {
+                    if sides[idx] && !covered[idx] {
+                        pieces.push((variants.side.as_ref(), idx as u32));
+                    }
+                }
+
+                for idx in 0..4 {
+                    if corners[idx] && !sides[idx] && !sides[(idx + 1) % 4] {
+                        pieces.push((variants.outer_corner.as_ref(), idx as u32));
+                    }
+                }
+            }
+        }
+
+        let scale = render_layers.scale();
+        let opts = self.render_opts();
+        let center = tile_center(position);
+
+        let mut count = 0;
+        for (salt, (sprite, direction)) in (0..).zip(pieces) {
+            let Some(res) = sprite.and_then(|sprite| {
+                sprite.render_transition(
+                    scale,
                     used_mods,
                     image_cache,
-                    &SimpleGraphicsRenderOpts {
-                        runtime_tint: Some(self.tint),
-                    },
+                    &opts,
+                    position_hash(position, salt),
+                    direction,
                 )
-            })
-            .map(|res| render_layers.add(res, position, InternalRenderLayer::Ground))
+            }) else {
+                continue;
+            };
+
+            render_layers.add(res, &center, InternalRenderLayer::Ground);
+            count += 1;
+        }
+
+        count
     }
 }
 
+fn tile_center((x, y): (i32, i32)) -> MapPosition {
+    MapPosition::Tuple(f64::from(x) + 0.5, f64::from(y) + 0.5)
+}
+
 /// [`Prototypes/TilePrototype`](https://lua-api.factorio.com/latest/prototypes/TilePrototype.html)
 #[skip_serializing_none]
 #[derive(Debug, Deserialize, Serialize)]
@@ -128,10 +318,31 @@ pub enum TileRenderLayer {
 }
 
 /// [`Types/TileTransitions`](https://lua-api.factorio.com/latest/types/TileTransitions.html)
+#[skip_serializing_none]
 #[derive(Debug, Deserialize, Serialize)]
 pub struct TileTransitions<T> {
+    pub inner_corner: Option<TileTransitionSprite>,
+    pub outer_corner: Option<TileTransitionSprite>,
+    pub side: Option<TileTransitionSprite>,
+    pub u_transition: Option<TileTransitionSprite>,
+    pub o_transition: Option<TileTransitionSprite>,
+
     #[serde(flatten)]
     child: T,
+    // not implemented
+    // pub inner_corner_mask: Option<TileTransitionSprite>,
+    // pub inner_corner_background: Option<TileTransitionSprite>,
+    // ... (same for the other transitions)
+    // pub empty_transitions: bool,
+    // pub layer: Option<u8>,
+    // pub overlay_layer_group: Option<TileRenderLayer>,
+    // pub background_layer_group: Option<TileRenderLayer>,
+    // pub overlay_layer_offset: Option<i8>,
+    // pub masked_overlay_layer_offset: i8,
+    // pub background_layer_offset: i8,
+    // pub masked_background_layer_offset: Option<i8>,
+    // pub apply_effect_color_to_overlay: bool,
+    // pub offset_background_layer_by_tile_layer: bool,
 }
 
 impl<T> std::ops::Deref for TileTransitions<T> {
@@ -0,0 +1,404 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    sync::atomic::AtomicBool,
+};
+
+use error_stack::{ensure, report, AttachmentKind, FrameKind, Report, Result, ResultExt};
+use factorio_api::PortalCache;
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+use types::ImageCache;
+
+use crate::{
+    encode_png, load_requirements, log_progress, preset, render_image, Requirements, ScannerError,
+};
+
+/// A single line of a JSON lines input file, either the plain blueprint string or an object with an id.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum JsonLine {
+    String(String),
+    Entry { id: String, string: String },
+}
+
+struct Job {
+    id: String,
+    bp: blueprint::Data,
+    requirements: Requirements,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Serialize)]
+struct ReportEntry {
+    id: String,
+    file: Option<PathBuf>,
+    thumbnail: Option<PathBuf>,
+    missing: Vec<String>,
+    error: Option<Vec<String>>,
+}
+
+impl ReportEntry {
+    const fn new(id: String) -> Self {
+        Self {
+            id,
+            file: None,
+            thumbnail: None,
+            missing: Vec::new(),
+            error: None,
+        }
+    }
+
+    fn failed(id: String, err: &Report<ScannerError>) -> Self {
+        Self {
+            error: Some(error_chain(err)),
+            ..Self::new(id)
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct BatchReport {
+    succeeded: usize,
+    failed: usize,
+    entries: Vec<ReportEntry>,
+}
+
+/// Flattens the contexts & printable attachments of a report into a list of messages.
+fn error_chain(err: &Report<ScannerError>) -> Vec<String> {
+    err.frames()
+        .filter_map(|frame| match frame.kind() {
+            FrameKind::Context(context) => Some(context.to_string()),
+            FrameKind::Attachment(AttachmentKind::Printable(attachment)) => {
+                Some(attachment.to_string())
+            }
+            FrameKind::Attachment(_) => None,
+        })
+        .collect()
+}
+
+/// Id of an input together with its blueprint string or the reason why it couldn't be read.
+type BatchInput = (String, std::result::Result<String, String>);
+
+/// Ids are used as file names in the output directory, so they must not be able to point anywhere else.
+fn check_id(id: &str) -> std::result::Result<(), String> {
+    if id.trim().is_empty() {
+        return Err("the id is empty".to_owned());
+    }
+
+    if id.contains(['/', '\\']) || id.contains("..") {
+        return Err(format!(
+            "the id {id:?} must not contain path separators or \"..\""
+        ));
+    }
+
+    // `<id>.thumb.png` is the thumbnail of `<id>`
+    if Path::new(id)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("thumb"))
+    {
+        return Err(format!(
+            "the id {id:?} must not end with \".thumb\", that name is used for thumbnails"
+        ));
+    }
+
+    Ok(())
+}
+
+/// Marks inputs with an invalid id or an id that was already used by an earlier input as failed.
+///
+/// Inputs that couldn't be read still claim their id.
+fn check_ids(inputs: Vec<BatchInput>) -> Vec<BatchInput> {
+    let mut seen = HashSet::with_capacity(inputs.len());
+
+    inputs
+        .into_iter()
+        .map(|(id, content)| {
+            let checked = check_id(&id).and_then(|()| {
+                if seen.insert(id.clone()) {
+                    Ok(())
+                } else {
+                    Err(format!("the id {id:?} is used by multiple inputs"))
+                }
+            });
+
+            (id, checked.and(content))
+        })
+        .collect()
+}
+
+/// Reads the blueprint strings from a directory of `.txt` files or a JSON lines file.
+fn read_input(input: &Path) -> Result<Vec<BatchInput>, ScannerError> {
+    if input.is_dir() {
+        let mut files = fs::read_dir(input)
+            .change_context(ScannerError::SetupError)
+            .attach_printable_lazy(|| {
+                format!("failed to read input directory {}", input.display())
+            })?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "txt"))
+            .collect::<Vec<_>>();
+        files.sort();
+
+        return Ok(files
+            .into_iter()
+            .map(|path| {
+                let id = path
+                    .file_stem()
+                    .map_or_else(String::new, |s| s.to_string_lossy().to_string());
+                let content = fs::read_to_string(&path).map_err(|err| err.to_string());
+
+                (id, content)
+            })
+            .collect());
+    }
+
+    let content = fs::read_to_string(input)
+        .change_context(ScannerError::SetupError)
+        .attach_printable_lazy(|| format!("failed to read input file {}", input.display()))?;
+
+    Ok(content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(idx, line)| {
+            let line_id = (idx + 1).to_string();
+
+            match serde_json::from_str::<JsonLine>(line) {
+                Ok(JsonLine::String(string)) => (line_id, Ok(string)),
+                Ok(JsonLine::Entry { id, string }) => (id, Ok(string)),
+                Err(err) => (line_id, Err(err.to_string())),
+            }
+        })
+        .collect())
+}
+
+#[allow(clippy::too_many_arguments, clippy::too_many_lines)]
+pub fn run(
+    input: &Path,
+    factorio: &Path,
+    factorio_bin: &Path,
+    portal_cache: &PortalCache,
+    preset: Option<preset::Preset>,
+    mods: &[String],
+    prototype_dump: Option<&Path>,
+    target_res: f64,
+    out: &Path,
+    report_path: Option<PathBuf>,
+    mut image_cache: ImageCache,
+) -> Result<(), ScannerError> {
+    let inputs = check_ids(read_input(input)?);
+    ensure!(!inputs.is_empty(), ScannerError::NoBlueprint);
+
+    fs::create_dir_all(out)
+        .change_context(ScannerError::SetupError)
+        .attach_printable_lazy(|| format!("failed to create output directory {}", out.display()))?;
+
+    let mut entries = Vec::with_capacity(inputs.len());
+
+    // group the blueprints by their required mods & settings so that the data is only loaded once per set
+    let mut groups = BTreeMap::<String, Vec<Job>>::new();
+    for (id, bp_string) in inputs {
+        let job = bp_string
+            .map_err(|err| report!(ScannerError::NoBlueprint).attach_printable(err))
+            .and_then(|bp_string| {
+                blueprint::Data::try_from(bp_string).change_context(ScannerError::NoBlueprint)
+            })
+            .and_then(|bp| {
+                Requirements::from_bp(&bp, preset, mods).map(|requirements| Job {
+                    id: id.clone(),
+                    bp,
+                    requirements,
+                })
+            });
+
+        match job {
+            Ok(job) => groups.entry(job.requirements.key()).or_default().push(job),
+            Err(err) => {
+                warn!("failed to load blueprint {id}: {err:?}");
+                entries.push(ReportEntry::failed(id, &err));
+            }
+        }
+    }
+
+    info!(
+        "rendering {} blueprints with {} different mod sets",
+        groups.values().map(Vec::len).sum::<usize>(),
+        groups.len()
+    );
+
+    for jobs in groups.into_values() {
+        let data = load_requirements(
+            &jobs[0].requirements,
+            factorio,
+            factorio_bin,
+            portal_cache,
+            prototype_dump.map(Path::to_path_buf),
+            &log_progress,
+        );
+
+        let (data, used_mods) = match data {
+            Ok(data) => data,
+            Err(err) => {
+                warn!("failed to load data for {} blueprints: {err:?}", jobs.len());
+                entries.extend(
+                    jobs.into_iter()
+                        .map(|job| ReportEntry::failed(job.id, &err)),
+                );
+                continue;
+            }
+        };
+
+        for job in jobs {
+            let file = out.join(format!("{}.png", job.id));
+
+            let entry = render_image(
+                &job.bp,
+                &data,
+                &used_mods,
+                target_res,
+                &mut image_cache,
+                &log_progress,
+                &AtomicBool::new(false),
+            )
+            .and_then(|(img, missing, thumb)| {
+                fs::write(&file, encode_png(&img)?).change_context(ScannerError::RenderError)?;
+
+                let mut entry = ReportEntry::new(job.id.clone());
+                entry.missing = missing.into_iter().collect();
+                entry.missing.sort();
+
+                if let Some(thumb) = thumb {
+                    let thumb_file = file.with_extension("thumb.png");
+                    fs::write(&thumb_file, encode_png(&thumb)?)
+                        .change_context(ScannerError::RenderError)?;
+                    entry.thumbnail = Some(thumb_file);
+                }
+
+                entry.file = Some(file);
+                Ok(entry)
+            });
+
+            match entry {
+                Ok(entry) => {
+                    if !entry.missing.is_empty() {
+                        warn!("missing prototypes in {}: {:?}", job.id, entry.missing);
+                    }
+
+                    info!("rendered {}", job.id);
+                    entries.push(entry);
+                }
+                Err(err) => {
+                    warn!("failed to render {}: {err:?}", job.id);
+                    entries.push(ReportEntry::failed(job.id, &err));
+                }
+            }
+        }
+    }
+
+    entries.sort_by(|a, b| a.id.cmp(&b.id));
+
+    let failed = entries.iter().filter(|e| e.error.is_some()).count();
+    let report = BatchReport {
+        succeeded: entries.len() - failed,
+        failed,
+        entries,
+    };
+
+    info!(
+        "batch finished: {} succeeded, {} failed",
+        report.succeeded, report.failed
+    );
+
+    let report_path = report_path.unwrap_or_else(|| out.join("report.json"));
+    fs::write(
+        &report_path,
+        serde_json::to_vec_pretty(&report).change_context(ScannerError::RenderError)?,
+    )
+    .change_context(ScannerError::RenderError)?;
+    info!("saved report to {}", report_path.display());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    fn input(id: &str, content: std::result::Result<&str, &str>) -> BatchInput {
+        (
+            id.to_owned(),
+            content.map(str::to_owned).map_err(str::to_owned),
+        )
+    }
+
+    #[test]
+    fn rejects_ids_that_leave_the_output() {
+        for id in ["", " ", "a/b", "a\\b", "..", "../a", "a.thumb", "a.THUMB"] {
+            assert!(check_id(id).is_err(), "{id:?} was accepted");
+        }
+
+        for id in ["a", "1", "my blueprint", "a.b", "thumb"] {
+            assert!(check_id(id).is_ok(), "{id:?} was rejected");
+        }
+    }
+
+    #[test]
+    fn rejects_duplicate_ids() {
+        let checked = check_ids(vec![
+            input("a", Err("unreadable")),
+            input("a", Ok("0abc")),
+            input("b", Ok("0def")),
+            input("b", Ok("0ghi")),
+        ]);
+
+        assert_eq!(checked[0].1, Err("unreadable".to_owned()));
+        assert!(checked[1]
+            .1
+            .as_ref()
+            .unwrap_err()
+            .contains("multiple inputs"));
+        assert_eq!(checked[2].1, Ok("0def".to_owned()));
+        assert!(checked[3]
+            .1
+            .as_ref()
+            .unwrap_err()
+            .contains("multiple inputs"));
+    }
+
+    #[test]
+    fn reads_json_lines() {
+        let path = std::env::temp_dir().join(format!("batch-input-{}.jsonl", std::process::id()));
+        fs::write(
+            &path,
+            "\"0abc\"\n\n{\"id\": \"named\", \"string\": \"0def\"}\nnot json\n",
+        )
+        .unwrap();
+
+        let inputs = read_input(&path).unwrap();
+        fs::remove_file(&path).ok();
+
+        assert_eq!(inputs.len(), 3);
+        assert_eq!(inputs[0], input("1", Ok("0abc")));
+        assert_eq!(inputs[1], input("named", Ok("0def")));
+        assert_eq!(inputs[2].0, "4");
+        assert!(inputs[2].1.is_err());
+    }
+
+    #[test]
+    fn reads_txt_files_of_a_directory() {
+        let dir = std::env::temp_dir().join(format!("batch-input-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("b.txt"), "0def").unwrap();
+        fs::write(dir.join("a.txt"), "0abc").unwrap();
+        fs::write(dir.join("ignored.json"), "{}").unwrap();
+
+        let inputs = read_input(&dir).unwrap();
+        fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(inputs, vec![input("a", Ok("0abc")), input("b", Ok("0def"))]);
+    }
+}
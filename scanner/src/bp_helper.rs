@@ -67,6 +67,30 @@ pub fn get_used_versions(bp: &blueprint::Blueprint) -> DependencyList {
     auto_detected
 }
 
+/// Planners have no meta info, so the used mods can only be guessed from the names they reference.
+#[must_use]
+pub fn get_planner_used_versions(data: &blueprint::Data) -> DependencyList {
+    let mut auto_detected = DependencyList::new();
+
+    match data {
+        blueprint::Data::UpgradePlanner(planner) => {
+            for mapper in &planner.mappers {
+                for value in [&mapper.from, &mapper.to].into_iter().flatten() {
+                    check_prefix(value.name(), &mut auto_detected);
+                }
+            }
+        }
+        blueprint::Data::DeconstructionPlanner(planner) => {
+            for filter in planner.entity_filters.iter().chain(&planner.tile_filters) {
+                check_prefix(filter, &mut auto_detected);
+            }
+        }
+        _ => {}
+    }
+
+    auto_detected
+}
+
 fn check_prefix(id: &str, dep_list: &mut DependencyList) {
     for preset in Preset::iter() {
         let Some(prefix) = preset.known_prefix() else {
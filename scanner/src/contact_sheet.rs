@@ -0,0 +1,133 @@
+use image::{imageops, DynamicImage, GenericImageView, Rgba, RgbaImage};
+use prototypes::text::Font;
+
+const PADDING: u32 = 16;
+const BACKGROUND: Rgba<u8> = Rgba([0x1b, 0x1b, 0x1b, 0xff]);
+const CELL_BACKGROUND: Rgba<u8> = Rgba([0x31, 0x31, 0x31, 0xff]);
+const LABEL_COLOR: types::Color = types::Color::RGBA(1.0, 0.9, 0.75, 1.0);
+const COUNT_COLOR: types::Color = types::Color::RGBA(0.7, 0.7, 0.7, 1.0);
+
+#[derive(Debug, Clone, Copy)]
+pub struct Layout {
+    pub columns: u32,
+    pub cell_size: u32,
+}
+
+impl Layout {
+    const fn header_size(self) -> u32 {
+        self.cell_size / 4
+    }
+
+    /// Scales a render down so that it fits into a single cell of the sheet.
+    #[must_use]
+    pub fn fit_render(self, img: &DynamicImage) -> DynamicImage {
+        if img.width() <= self.cell_size && img.height() <= self.cell_size {
+            return img.clone();
+        }
+
+        img.resize(
+            self.cell_size,
+            self.cell_size,
+            imageops::FilterType::CatmullRom,
+        )
+    }
+
+    fn fit_header(self, img: &DynamicImage) -> DynamicImage {
+        let size = self.header_size();
+        img.resize(size, size, imageops::FilterType::CatmullRom)
+    }
+}
+
+/// A single page on the contact sheet: its (optional) thumbnail and the render itself.
+pub struct Page {
+    pub thumbnail: Option<DynamicImage>,
+    pub render: DynamicImage,
+    pub label: String,
+    pub entities: usize,
+}
+
+/// Draws the label & entity count of a page next to its thumbnail.
+fn draw_header_text(
+    sheet: &mut RgbaImage,
+    font: &Font,
+    page: &Page,
+    (x, y): (u32, u32),
+    (width, height): (u32, u32),
+) {
+    let label_size = f64::from(height) * 0.4;
+    let count_size = f64::from(height) * 0.3;
+
+    let label = font.fit(&page.label, label_size, width);
+    if let Some(label) = font.render(&label, label_size, LABEL_COLOR) {
+        imageops::overlay(sheet, &label, x.into(), y.into());
+    }
+
+    let count = match page.entities {
+        1 => "1 entity".to_owned(),
+        n => format!("{n} entities"),
+    };
+    if let Some(count) = font.render(&count, count_size, COUNT_COLOR) {
+        let count_y = y + height - count.height().min(height);
+        imageops::overlay(sheet, &count, x.into(), count_y.into());
+    }
+}
+
+/// Lays out all pages in a grid, each cell shows the thumbnail of the page above its render.
+///
+/// The label & entity count of a page are drawn next to its thumbnail if a font is given.
+#[must_use]
+pub fn compose(pages: &[Page], layout: Layout, font: Option<&Font>) -> Option<DynamicImage> {
+    if pages.is_empty() || layout.columns == 0 || layout.cell_size == 0 {
+        return None;
+    }
+
+    let count = u32::try_from(pages.len()).ok()?;
+    let columns = layout.columns.min(count);
+    let rows = count.div_ceil(columns);
+
+    let header = layout.header_size();
+    let cell_width = layout.cell_size;
+    let cell_height = header + PADDING / 2 + layout.cell_size;
+
+    let width = columns * (cell_width + PADDING) + PADDING;
+    let height = rows * (cell_height + PADDING) + PADDING;
+
+    let mut sheet = RgbaImage::from_pixel(width, height, BACKGROUND);
+
+    for (idx, page) in (0..count).zip(pages) {
+        let cell_x = PADDING + (idx % columns) * (cell_width + PADDING);
+        let cell_y = PADDING + (idx / columns) * (cell_height + PADDING);
+
+        let cell = RgbaImage::from_pixel(cell_width, cell_height, CELL_BACKGROUND);
+        imageops::overlay(&mut sheet, &cell, cell_x.into(), cell_y.into());
+
+        if let Some(thumbnail) = &page.thumbnail {
+            let thumbnail = layout.fit_header(thumbnail);
+            imageops::overlay(&mut sheet, &thumbnail, cell_x.into(), cell_y.into());
+        }
+
+        if let Some(font) = font {
+            let text_x = header + PADDING / 2;
+            draw_header_text(
+                &mut sheet,
+                font,
+                page,
+                (cell_x + text_x, cell_y),
+                (cell_width.saturating_sub(text_x + PADDING / 2), header),
+            );
+        }
+
+        // center the render inside the area below the header
+        let render = layout.fit_render(&page.render);
+        let (render_width, render_height) = render.dimensions();
+        let render_x = cell_x + (cell_width - render_width.min(cell_width)) / 2;
+        let render_y = cell_y
+            + header
+            + PADDING / 2
+            + (layout.cell_size - render_height.min(layout.cell_size)) / 2;
+
+        imageops::overlay(&mut sheet, &render, render_x.into(), render_y.into());
+    }
+
+    Some(sheet.into())
+}
@@ -0,0 +1,317 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    sync::atomic::AtomicBool,
+};
+
+use error_stack::{Result, ResultExt};
+use image::{imageops, DynamicImage, RgbaImage};
+use mod_util::UsedMods;
+use prototypes::{tile::TilePrototype, DataUtil, RenderLayerBuffer, TargetSize};
+use serde::Serialize;
+use types::{ImageCache, MapPosition};
+
+use crate::{calculate_bounds, encode_png, render_bp, ScannerError};
+
+/// Size (1 side of a square) of a single output tile in pixels.
+const TILE_SIZE: u32 = 256;
+
+/// Output tiles per side of a region that is rendered at once, bounds the memory usage.
+const REGION_TILES: u32 = 8;
+
+/// Entities within this distance (in map tiles) of a region are rendered into it as well
+/// so that wires and overhanging sprites continue across region borders.
+const REGION_MARGIN: f64 = 32.0;
+
+/// Tile URL template (Leaflet / XYZ style) relative to the manifest.
+#[allow(clippy::literal_string_with_formatting_args)]
+const URL_TEMPLATE: &str = "{z}/{x}/{y}.png";
+
+/// Description of the tile pyramid, written next to the tiles.
+#[derive(Debug, Serialize)]
+struct Manifest {
+    url: &'static str,
+    tile_size: u32,
+    min_zoom: u32,
+    max_zoom: u32,
+
+    /// Size of the full resolution image in pixels.
+    width: u32,
+    height: u32,
+
+    /// Pixels per map tile at the highest zoom level.
+    tile_resolution: f64,
+
+    /// Map position of the top left corner of the image.
+    top_left: (f64, f64),
+}
+
+struct Pyramid<'a> {
+    out: &'a Path,
+    columns: u32,
+    rows: u32,
+    max_zoom: u32,
+}
+
+impl Pyramid<'_> {
+    fn tile_path(&self, zoom: u32, x: u32, y: u32) -> PathBuf {
+        self.out
+            .join(zoom.to_string())
+            .join(x.to_string())
+            .join(format!("{y}.png"))
+    }
+
+    /// Number of tile columns & rows of a zoom level.
+    const fn level_size(&self, zoom: u32) -> (u32, u32) {
+        let factor = 1 << (self.max_zoom - zoom);
+        (self.columns.div_ceil(factor), self.rows.div_ceil(factor))
+    }
+
+    fn write_tile(
+        &self,
+        zoom: u32,
+        x: u32,
+        y: u32,
+        img: &DynamicImage,
+    ) -> Result<(), ScannerError> {
+        let path = self.tile_path(zoom, x, y);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .change_context(ScannerError::RenderError)
+                .attach_printable_lazy(|| format!("failed to create {}", parent.display()))?;
+        }
+
+        fs::write(&path, encode_png(img)?)
+            .change_context(ScannerError::RenderError)
+            .attach_printable_lazy(|| format!("failed to write {}", path.display()))
+    }
+
+    /// Builds a tile from its (up to 4) children of the next zoom level, returns `false` if none of them exist.
+    fn downsample(&self, zoom: u32, x: u32, y: u32) -> Result<bool, ScannerError> {
+        let mut canvas = RgbaImage::new(TILE_SIZE * 2, TILE_SIZE * 2);
+        let mut found = false;
+
+        for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+            let path = self.tile_path(zoom + 1, x * 2 + dx, y * 2 + dy);
+            if !path.exists() {
+                continue;
+            }
+
+            let child = image::open(&path)
+                .change_context(ScannerError::RenderError)
+                .attach_printable_lazy(|| format!("failed to read {}", path.display()))?;
+
+            imageops::overlay(
+                &mut canvas,
+                &child,
+                i64::from(dx * TILE_SIZE),
+                i64::from(dy * TILE_SIZE),
+            );
+            found = true;
+        }
+
+        if found {
+            let tile = imageops::resize(
+                &canvas,
+                TILE_SIZE,
+                TILE_SIZE,
+                imageops::FilterType::Triangle,
+            );
+            self.write_tile(zoom, x, y, &tile.into())?;
+        }
+
+        Ok(found)
+    }
+}
+
+/// Distance (in map tiles) that tiles around a region can influence its content.
+///
+/// Multi tile variants are aligned to a grid of their size and only used if the whole block is covered,
+/// transitions reach 1 tile further.
+fn tile_margin(bp: &blueprint::Blueprint, data: &DataUtil) -> f64 {
+    bp.tiles
+        .iter()
+        .filter_map(|tile| data.get_tile(&tile.name))
+        .map(TilePrototype::max_variant_size)
+        .max()
+        .map_or(0.0, |size| f64::from(size) + 1.0)
+}
+
+/// Copy of the blueprint with only the entities & tiles that are visible in the given area.
+///
+/// Tiles are kept within `tile_margin` of the area so that they pick the same variants & transitions in every region.
+fn region_blueprint(
+    bp: &blueprint::Blueprint,
+    data: &DataUtil,
+    (top_left, bottom_right): (&MapPosition, &MapPosition),
+    tile_margin: f64,
+) -> blueprint::Blueprint {
+    let (min_x, min_y) = top_left.as_tuple();
+    let (max_x, max_y) = bottom_right.as_tuple();
+
+    let overlaps = |tl: &MapPosition, br: &MapPosition, margin: f64| {
+        br.x() >= min_x - margin
+            && br.y() >= min_y - margin
+            && tl.x() <= max_x + margin
+            && tl.y() <= max_y + margin
+    };
+
+    let mut region = bp.clone();
+    region.entities.retain(|entity| {
+        let position: MapPosition = (&entity.position).into();
+
+        data.get_entity(&entity.name).map_or_else(
+            || overlaps(&position, &position, REGION_MARGIN),
+            |proto| {
+                let drawing_box = proto.drawing_box();
+                overlaps(
+                    &(&position + drawing_box.top_left()),
+                    &(&position + drawing_box.bottom_right()),
+                    REGION_MARGIN,
+                )
+            },
+        )
+    });
+
+    region.tiles.retain(|tile| {
+        let position: MapPosition = (&tile.position).into();
+        overlaps(&position, &position, tile_margin)
+    });
+
+    region
+}
+
+/// Renders a blueprint region by region into a tile pyramid (`<zoom>/<x>/<y>.png`) in `out`.
+///
+/// The highest zoom level shows the blueprint at the given `scale`, every lower level halves the resolution.
+/// Regions without any entities or tiles are skipped, so the pyramid has no tiles where the blueprint is empty.
+/// A `tiles.json` with the size of the pyramid is written to `out` as well.
+#[allow(clippy::too_many_lines)]
+pub fn render(
+    bp: &blueprint::Blueprint,
+    data: &DataUtil,
+    used_mods: &UsedMods,
+    scale: f64,
+    out: &Path,
+    image_cache: &mut ImageCache,
+) -> Result<HashSet<String>, ScannerError> {
+    const TILE_RES: f64 = 32.0;
+
+    let (top_left, bottom_right) = calculate_bounds(bp, data).ok_or(ScannerError::RenderError)?;
+    let tile_res = TILE_RES / scale;
+    let width = ((bottom_right.x() - top_left.x()) * tile_res).ceil() as u32;
+    let height = ((bottom_right.y() - top_left.y()) * tile_res).ceil() as u32;
+
+    let columns = width.div_ceil(TILE_SIZE);
+    let rows = height.div_ceil(TILE_SIZE);
+    let max_zoom = columns.max(rows).next_power_of_two().trailing_zeros();
+
+    let pyramid = Pyramid {
+        out,
+        columns,
+        rows,
+        max_zoom,
+    };
+
+    let tile_margin = tile_margin(bp, data);
+    let region_px = TILE_SIZE * REGION_TILES;
+    let region_size = f64::from(region_px) / tile_res;
+    let region_columns = columns.div_ceil(REGION_TILES);
+    let region_rows = rows.div_ceil(REGION_TILES);
+
+    info!(
+        "rendering {width}x{height} px in {} regions, {} zoom levels",
+        region_columns * region_rows,
+        max_zoom + 1
+    );
+
+    let mut unknown = HashSet::new();
+    for region_y in 0..region_rows {
+        for region_x in 0..region_columns {
+            let region_tl = &top_left
+                + &MapPosition::XY {
+                    x: f64::from(region_x) * region_size,
+                    y: f64::from(region_y) * region_size,
+                };
+            let region_br = &region_tl
+                + &MapPosition::XY {
+                    x: region_size,
+                    y: region_size,
+                };
+
+            let region = region_blueprint(bp, data, (&region_tl, &region_br), tile_margin);
+            if region.entities.is_empty() && region.tiles.is_empty() {
+                continue;
+            }
+
+            let mut render_layers = RenderLayerBuffer::new(TargetSize::new(
+                region_px, region_px, scale, region_tl, region_br,
+            ));
+
+            unknown.extend(
+                render_bp(
+                    &region,
+                    data,
+                    used_mods,
+                    &mut render_layers,
+                    image_cache,
+                    &|_| {},
+                    &AtomicBool::new(false),
+                )
+                .ok_or(ScannerError::RenderError)?,
+            );
+
+            let img = render_layers.combine();
+            for tile_y in 0..REGION_TILES {
+                for tile_x in 0..REGION_TILES {
+                    let x = region_x * REGION_TILES + tile_x;
+                    let y = region_y * REGION_TILES + tile_y;
+                    if x >= columns || y >= rows {
+                        continue;
+                    }
+
+                    let tile =
+                        img.crop_imm(tile_x * TILE_SIZE, tile_y * TILE_SIZE, TILE_SIZE, TILE_SIZE);
+                    pyramid.write_tile(max_zoom, x, y, &tile)?;
+                }
+            }
+
+            info!(
+                "rendered region {}/{}",
+                region_y * region_columns + region_x + 1,
+                region_columns * region_rows
+            );
+        }
+    }
+
+    for zoom in (0..max_zoom).rev() {
+        let (level_columns, level_rows) = pyramid.level_size(zoom);
+        for y in 0..level_rows {
+            for x in 0..level_columns {
+                pyramid.downsample(zoom, x, y)?;
+            }
+        }
+    }
+
+    let manifest = Manifest {
+        url: URL_TEMPLATE,
+        tile_size: TILE_SIZE,
+        min_zoom: 0,
+        max_zoom,
+        width,
+        height,
+        tile_resolution: tile_res,
+        top_left: top_left.as_tuple(),
+    };
+
+    let manifest_path = out.join("tiles.json");
+    fs::write(
+        &manifest_path,
+        serde_json::to_vec_pretty(&manifest).change_context(ScannerError::RenderError)?,
+    )
+    .change_context(ScannerError::RenderError)?;
+    info!("saved tile manifest to {manifest_path:?}");
+
+    Ok(unknown)
+}
@@ -1,6 +1,7 @@
 #![allow(dead_code, clippy::upper_case_acronyms, unused_variables)]
 
 use std::{
+    cell::RefCell,
     collections::{BTreeMap, HashMap, HashSet},
     env,
     fs::{self},
@@ -9,11 +10,13 @@ use std::{
     net::IpAddr,
     path::{Path, PathBuf},
     process::{Command, ExitCode},
+    sync::atomic::{AtomicBool, Ordering},
 };
 
 use blueprint::{ConnectionDataExt, SignalID};
 use clap::{Parser, Subcommand};
-use error_stack::{ensure, report, Context, Result, ResultExt};
+use error_stack::{ensure, report, Context, Report, Result, ResultExt};
+use factorio_api::{ModDownload, PortalCache};
 use flate2::{read::ZlibDecoder, write::ZlibEncoder};
 use image::{codecs::png, imageops, ImageEncoder};
 use serde::{Deserialize, Serialize};
@@ -32,14 +35,19 @@ use mod_util::{
 use prototypes::{
     entity::Type as EntityType, ConnectedEntities, EntityWireConnections, InternalRenderLayer,
 };
-use prototypes::{DataRaw, DataUtil, RenderLayerBuffer, TargetSize};
+use prototypes::{text::Font, DataRaw, DataUtil, RenderLayerBuffer, TargetSize};
 use types::{
-    ConnectedDirections, Direction, ImageCache, MapPosition, RenderableGraphics,
-    SimpleGraphicsRenderOpts, Vector,
+    ConnectedDirections, Direction, ImageCache, MapPosition, ModuleSpecification,
+    PipeConnectionType, RenderableGraphics, SimpleGraphicsRenderOpts, Vector,
 };
 
+mod batch;
 mod bp_helper;
+mod contact_sheet;
+mod deep_zoom;
+mod planner_card;
 mod preset;
+mod schedules;
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
@@ -59,6 +67,9 @@ struct Cli {
     #[clap(long, value_parser)]
     factorio_bin: Option<PathBuf>,
 
+    #[clap(flatten)]
+    portal_cache: PortalCacheArgs,
+
     #[clap(subcommand)]
     command: Commands,
 }
@@ -93,6 +104,68 @@ enum Commands {
         /// Minimum scale to use (below 0.5 makes not much sense, vanilla HR mode is 0.5)
         #[clap(long, default_value_t = 0.5)]
         min_scale: f64,
+
+        /// Render every blueprint of a blueprint book into its own file (named after the output file)
+        /// and write a manifest listing all rendered pages
+        #[clap(long, verbatim_doc_comment)]
+        book: bool,
+
+        /// Additionally compose all blueprints of the book into a single contact sheet image
+        #[clap(long, requires = "book")]
+        contact_sheet: bool,
+
+        /// Number of columns of the contact sheet
+        #[clap(long, default_value_t = 4)]
+        sheet_columns: u32,
+
+        /// Maximum size (1 side of a square) of a single render on the contact sheet in pixels
+        #[clap(long, default_value_t = 512)]
+        sheet_cell_size: u32,
+
+        /// List the train schedules of the blueprint in a sidebar next to the render
+        #[clap(long)]
+        schedules: bool,
+
+        /// Output format, SVG embeds every sprite and groups them by render layer.
+        /// Tiles renders a `<zoom>/<x>/<y>.png` pyramid at `min-scale` into the output directory
+        #[clap(long, value_enum, default_value_t = OutputFormat::Png, conflicts_with_all = ["book", "schedules"], verbatim_doc_comment)]
+        format: OutputFormat,
+    },
+
+    /// Render all blueprint strings of a directory (`.txt` files) or a JSON lines file
+    /// The data is only loaded once for every distinct set of required mods
+    #[clap(verbatim_doc_comment)]
+    Batch {
+        /// Directory with `.txt` files or JSON lines file (each line is a string or an object with `id` & `string`)
+        #[clap(value_parser)]
+        input: PathBuf,
+
+        /// Path to the data dump json file. If not set, the data will be dumped automatically
+        #[clap(long, value_parser)]
+        prototype_dump: Option<PathBuf>,
+
+        /// Preset to use for all blueprints
+        #[clap(long, value_enum)]
+        preset: Option<preset::Preset>,
+
+        /// List of additional mods to use
+        #[clap(long, value_parser, use_value_delimiter = true, value_delimiter = ',')]
+        mods: Vec<String>,
+
+        /// Path to the output directory
+        #[clap(short, long, value_parser)]
+        out: PathBuf,
+
+        /// Target resolution (1 side of a square) in pixels
+        #[clap(long = "res", default_value_t = 2048.0)]
+        target_res: f64,
+
+        /// Path to the summary report, defaults to `report.json` in the output directory
+        #[clap(long, value_parser)]
+        report: Option<PathBuf>,
+
+        #[clap(flatten)]
+        image_cache: ImageCacheArgs,
     },
 
     /// Run scanner as a server so that other applications can use it through its WebSocket API
@@ -108,9 +181,77 @@ enum Commands {
         /// Maximum queue size for incoming requests
         #[clap(long, default_value = "20")]
         max_queue: usize,
+
+        /// Number of requests that are processed in parallel
+        #[clap(long, default_value = "2")]
+        workers: usize,
+
+        /// Time in seconds after which a request is answered with a timeout error
+        #[clap(long, default_value = "300")]
+        timeout: u64,
+
+        /// Number of prototype data sets (mod sets) that are kept loaded
+        #[clap(long, default_value = "3")]
+        data_sets: usize,
+
+        #[clap(flatten)]
+        image_cache: ImageCacheArgs,
     },
 }
 
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    Png,
+    Svg,
+    Tiles,
+}
+
+#[derive(clap::Args, Debug)]
+struct ImageCacheArgs {
+    /// Maximum size of the decoded sprites that are kept in memory between renders in MiB
+    #[clap(long = "image-cache-size", default_value_t = 1024)]
+    size: usize,
+
+    /// Directory to store decoded sprites in so that they can be reused after a restart
+    #[clap(long = "image-cache-dir", value_parser)]
+    dir: Option<PathBuf>,
+}
+
+impl ImageCacheArgs {
+    fn build(self) -> ImageCache {
+        let cache = ImageCache::new().with_max_size(self.size * 1024 * 1024);
+
+        match self.dir {
+            Some(dir) => cache.with_disk_cache(dir),
+            None => cache,
+        }
+    }
+}
+
+#[derive(clap::Args, Debug)]
+struct PortalCacheArgs {
+    /// Directory to cache mod portal info in, defaults to `portal-cache` in the factorio directory
+    #[clap(long = "portal-cache", value_parser)]
+    dir: Option<PathBuf>,
+
+    /// Hours after which cached mod portal info is revalidated
+    #[clap(long = "portal-cache-max-age", default_value_t = 24)]
+    max_age: u64,
+
+    /// Never contact the mod portal, dependencies are solved with the cached portal info
+    /// and the installed mods only
+    #[clap(long, verbatim_doc_comment)]
+    offline: bool,
+}
+
+impl PortalCacheArgs {
+    fn build(self, factorio: &Path) -> PortalCache {
+        PortalCache::new(self.dir.unwrap_or_else(|| factorio.join("portal-cache")))
+            .max_age(std::time::Duration::from_secs(self.max_age * 60 * 60))
+            .offline(self.offline)
+    }
+}
+
 #[derive(Subcommand, Debug)]
 enum Input {
     String {
@@ -167,6 +308,59 @@ impl std::fmt::Display for ScannerError {
     }
 }
 
+/// Stages of loading the prototype data & rendering a blueprint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Progress {
+    /// Waiting in the server queue, 0 = next in line.
+    Queued {
+        position: usize,
+    },
+    ResolvingMods,
+    DownloadingMod {
+        name: String,
+        version: Version,
+        downloaded: u64,
+        total: Option<u64>,
+    },
+    DumpingPrototypes,
+    Rendering {
+        done: usize,
+        total: usize,
+    },
+}
+
+impl std::fmt::Display for Progress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Queued { position } => write!(f, "queued at position {position}"),
+            Self::ResolvingMods => write!(f, "resolving mods"),
+            Self::DownloadingMod {
+                name,
+                version,
+                downloaded,
+                total: Some(total),
+            } => write!(
+                f,
+                "downloading {name} v{version} ({downloaded}/{total} bytes)"
+            ),
+            Self::DownloadingMod {
+                name,
+                version,
+                downloaded,
+                total: None,
+            } => write!(f, "downloading {name} v{version} ({downloaded} bytes)"),
+            Self::DumpingPrototypes => write!(f, "dumping prototypes"),
+            Self::Rendering { done, total } => write!(f, "rendering {done}/{total} entities"),
+        }
+    }
+}
+
+/// Progress reporter for the CLI, the important stages are already logged on their own.
+fn log_progress(progress: &Progress) {
+    trace!("{progress}");
+}
+
+#[allow(clippy::too_many_lines)]
 fn main() -> ExitCode {
     dotenv::dotenv().ok();
     let cli = Cli::parse();
@@ -191,6 +385,7 @@ fn main() -> ExitCode {
     let factorio_bin = cli
         .factorio_bin
         .unwrap_or_else(|| cli.factorio.join("bin/x64/factorio"));
+    let portal_cache = cli.portal_cache.build(&cli.factorio);
 
     if let Err(err) = match cli.command {
         Commands::Render {
@@ -201,23 +396,75 @@ fn main() -> ExitCode {
             out,
             target_res,
             min_scale,
+            book,
+            contact_sheet,
+            sheet_columns,
+            sheet_cell_size,
+            schedules,
+            format,
         } => render_command(
             input,
             &cli.factorio,
             &factorio_bin,
+            &portal_cache,
             preset,
             &mods,
             prototype_dump,
             target_res,
+            min_scale,
+            &out,
+            book,
+            contact_sheet.then_some(contact_sheet::Layout {
+                columns: sheet_columns,
+                cell_size: sheet_cell_size,
+            }),
+            schedules,
+            format,
+        ),
+        Commands::Batch {
+            input,
+            prototype_dump,
+            preset,
+            mods,
+            out,
+            target_res,
+            report,
+            image_cache,
+        } => batch::run(
+            &input,
+            &cli.factorio,
+            &factorio_bin,
+            &portal_cache,
+            preset,
+            &mods,
+            prototype_dump.as_deref(),
+            target_res,
             &out,
+            report,
+            image_cache.build(),
         ),
         #[cfg(feature = "server")]
         Commands::Server {
             address,
             port,
             max_queue,
-        } => server::run(&cli.factorio, &factorio_bin, address, port, max_queue)
-            .change_context(ScannerError::ServerError),
+            workers,
+            timeout,
+            data_sets,
+            image_cache,
+        } => server::run(
+            &cli.factorio,
+            &factorio_bin,
+            portal_cache,
+            address,
+            port,
+            max_queue,
+            workers,
+            std::time::Duration::from_secs(timeout),
+            data_sets,
+            image_cache.build(),
+        )
+        .change_context(ScannerError::ServerError),
 
         #[cfg(not(feature = "server"))]
         Commands::Server { .. } => {
@@ -232,17 +479,16 @@ fn main() -> ExitCode {
     }
 }
 
-#[allow(clippy::too_many_lines)]
-fn get_protodump(
-    factorio: &Path,
-    factorio_bin: &Path,
-    mod_list: &ModList,
-    (bp_settings, bp_version): (&BTreeMap<String, AnyBasic>, u64),
-) -> Result<DataRaw, ScannerError> {
-    // check if cached dump exists and load it if available
-    let cached_path = {
-        let mut active_mods = mod_list
-            .active_mods()
+/// Identifies a prototype dump by the hashes of the active mods and the used startup settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct DumpKey {
+    mods: u64,
+    settings: u64,
+}
+
+impl DumpKey {
+    fn new(active_mods: &UsedMods, settings: &BTreeMap<String, AnyBasic>) -> Self {
+        let mut active_mods = active_mods
             .values()
             .map(|m| format!("{}@{}", m.info.name, m.info.version))
             .collect::<Vec<_>>();
@@ -252,9 +498,9 @@ fn get_protodump(
         for mod_name in &active_mods {
             mod_name.hash(&mut hash);
         }
-        let mods_hash = hash.finish();
+        let mods = hash.finish();
 
-        let mut active_settings = bp_settings
+        let mut active_settings = settings
             .iter()
             .map(|(k, v)| format!("{k}={v}"))
             .collect::<Vec<_>>();
@@ -264,11 +510,29 @@ fn get_protodump(
         for setting in &active_settings {
             setting.hash(&mut hash);
         }
-        let settings_hash = hash.finish();
+        let settings = hash.finish();
 
-        let cached_path = factorio.join(format!(
-            "script-output/cached-dump_{mods_hash:X}-{settings_hash:X}.json.deflate"
-        ));
+        Self { mods, settings }
+    }
+}
+
+impl std::fmt::Display for DumpKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:X}-{:X}", self.mods, self.settings)
+    }
+}
+
+fn get_protodump(
+    factorio: &Path,
+    factorio_bin: &Path,
+    mod_list: &ModList,
+    key: DumpKey,
+    (bp_settings, bp_version): (&BTreeMap<String, AnyBasic>, u64),
+    progress: &dyn Fn(&Progress),
+) -> Result<DataRaw, ScannerError> {
+    // check if cached dump exists and load it if available
+    let cached_path = {
+        let cached_path = factorio.join(format!("script-output/cached-dump_{key}.json.deflate"));
 
         if cached_path.exists() {
             info!("loading cached prototype dump");
@@ -309,6 +573,7 @@ fn get_protodump(
     .change_context(ScannerError::SetupError)?;
     debug!("updated mod-settings.dat");
 
+    progress(&Progress::DumpingPrototypes);
     debug!("executing {factorio_bin:?} with --dump-data");
     let dump_out = Command::new(factorio_bin)
         .arg("--dump-data")
@@ -366,29 +631,127 @@ fn render_command(
     input: Input,
     factorio: &Path,
     factorio_bin: &Path,
+    portal_cache: &PortalCache,
     preset: Option<preset::Preset>,
     mods: &[String],
     prototype_dump: Option<PathBuf>,
     target_res: f64,
+    min_scale: f64,
     out: &Path,
+    book: bool,
+    sheet: Option<contact_sheet::Layout>,
+    schedules: bool,
+    format: OutputFormat,
 ) -> Result<(), ScannerError> {
     let bp_string = input
         .get_bp_string()
         .change_context(ScannerError::NoBlueprint)?;
 
     let bp = blueprint::Data::try_from(bp_string).change_context(ScannerError::NoBlueprint)?;
-    let (data, active_mods) = load_data(&bp, factorio, factorio_bin, preset, mods, prototype_dump)?;
-    let (res, missing, thumb) = render(&bp, &data, &active_mods, target_res)?;
+
+    if book {
+        return render_book(
+            &bp,
+            (factorio, factorio_bin),
+            portal_cache,
+            (preset, mods),
+            prototype_dump.as_deref(),
+            target_res,
+            out,
+            sheet,
+            schedules,
+        );
+    }
+
+    let (data, active_mods) = load_data(
+        &bp,
+        factorio,
+        factorio_bin,
+        portal_cache,
+        preset,
+        mods,
+        prototype_dump,
+        &log_progress,
+    )?;
+
+    if format == OutputFormat::Svg {
+        let (svg, missing) = render_svg(
+            &bp,
+            &data,
+            &active_mods,
+            target_res,
+            &mut ImageCache::new(),
+            &log_progress,
+        )?;
+
+        if !missing.is_empty() {
+            warn!("missing prototypes: {missing:?}");
+        }
+
+        fs::write(out, svg).change_context(ScannerError::RenderError)?;
+        info!("saved render to {out:?}");
+
+        return Ok(());
+    }
+
+    if format == OutputFormat::Tiles {
+        let bp = bp
+            .as_blueprint()
+            .ok_or_else(|| report!(ScannerError::NoBlueprint))
+            .attach_printable("only blueprints can be rendered as tiles")?;
+
+        let missing = deep_zoom::render(
+            bp,
+            &data,
+            &active_mods,
+            min_scale,
+            out,
+            &mut ImageCache::new(),
+        )?;
+
+        if !missing.is_empty() {
+            warn!("missing prototypes: {missing:?}");
+        }
+
+        info!("saved tiles to {out:?}");
+        return Ok(());
+    }
+
+    let schedule_font = if schedules {
+        let font = Font::load(&active_mods);
+        if font.is_none() {
+            warn!("no font available, skipping schedule sidebar");
+        }
+
+        font
+    } else {
+        None
+    };
+
+    let (img, missing, thumb) = render_image(
+        &bp,
+        &data,
+        &active_mods,
+        target_res,
+        &mut ImageCache::new(),
+        &log_progress,
+        &AtomicBool::new(false),
+    )?;
 
     if !missing.is_empty() {
         warn!("missing prototypes: {missing:?}");
     }
 
-    fs::write(out, res).change_context(ScannerError::RenderError)?;
+    let img = match (&schedule_font, bp.as_blueprint()) {
+        (Some(font), Some(bp)) => schedules::attach(img, &bp.schedules, font),
+        _ => img,
+    };
+
+    fs::write(out, encode_png(&img)?).change_context(ScannerError::RenderError)?;
     info!("saved render to {out:?}");
 
     if let Some(thumb) = thumb {
-        fs::write(out.with_extension("thumb.png"), thumb)
+        fs::write(out.with_extension("thumb.png"), encode_png(&thumb)?)
             .change_context(ScannerError::RenderError)?;
         info!("saved thumbnail to {:?}", out.with_extension("thumb.png"));
     }
@@ -396,153 +759,586 @@ fn render_command(
     Ok(())
 }
 
-fn load_data(
-    bp: &blueprint::Data,
-    factorio: &Path,
-    factorio_bin: &Path,
-    preset: Option<preset::Preset>,
-    mods: &[String],
-    prototype_dump: Option<PathBuf>,
-) -> Result<(DataUtil, UsedMods), ScannerError> {
-    let bp = bp
-        .as_blueprint()
-        .ok_or(report!(ScannerError::NoBlueprint))?;
+#[skip_serializing_none]
+#[derive(Debug, Serialize)]
+struct BookManifestEntry {
+    path: Vec<u16>,
+    index: Option<u16>,
+    label: String,
+    file: Option<PathBuf>,
+    thumbnail: Option<PathBuf>,
+    width: Option<u32>,
+    height: Option<u32>,
+    missing: Vec<String>,
+    error: Option<String>,
+}
 
-    info!("loaded BP");
+fn book_page_path(out: &Path, path: &[u16]) -> PathBuf {
+    let stem = out
+        .file_stem()
+        .map_or_else(|| "render".into(), |s| s.to_string_lossy());
 
-    let mut mod_list = ModList::generate(factorio).change_context(ScannerError::SetupError)?;
+    if path.is_empty() {
+        return out.with_file_name(format!("{stem}.png"));
+    }
 
-    // get used mods from preset or detect from BP meta info
-    let mut required_mods = std::iter::once((
-        "base".to_owned(),
-        DependencyVersion::Exact(prototypes::targeted_engine_version()),
-    ))
-    .collect::<HashMap<_, _>>();
-    required_mods.extend(
-        preset
-            .as_ref()
-            .map_or_else(|| bp_helper::get_used_versions(bp), |p| p.used_mods()),
-    );
-    required_mods.extend(mods.iter().map(|m| (m.clone(), DependencyVersion::Any)));
+    let path = path
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("-");
 
-    debug!(
-        "required mods: {}",
-        required_mods
-            .iter()
-            .map(|(n, v)| format!("{n} {v}"))
-            .collect::<Vec<_>>()
-            .join(", ")
-    );
+    out.with_file_name(format!("{stem}_{path}.png"))
+}
 
-    if !required_mods.is_empty() {
-        debug!("checking mod dependencies");
+/// Removes rich text tags from a label, icon tags are replaced by the name of the icon.
+///
+/// `[item=iron-plate] Drop` becomes `iron-plate Drop`, `[color=red]Stop[/color]` becomes `Stop`.
+fn strip_rich_text(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+    let mut rest = text;
 
-        mod_list.load_local_dependency_info(&required_mods);
-        let used_mods = resolve_mod_dependencies(&required_mods, &mut mod_list)
-            .change_context(ScannerError::SetupError)?;
+    while let Some(start) = rest.find('[') {
+        let Some(len) = rest[start..].find(']') else {
+            break;
+        };
 
-        let missing = mod_list.enable_mods(&used_mods);
-        if missing.is_empty() {
-            debug!("all mods are already installed");
-        } else {
-            info!("downloading missing mods from mod portal");
-            download_mods(missing, factorio).change_context(ScannerError::SetupError)?;
+        res.push_str(&rest[..start]);
+        let tag = &rest[start + 1..start + len];
+        rest = &rest[start + len + 1..];
+
+        match tag.split_once('=') {
+            Some(("color" | "font", _)) => {}
+            Some((_, value)) => res.push_str(value),
+            None if tag.starts_with('/') => {}
+            None => {
+                res.push('[');
+                res.push_str(tag);
+                res.push(']');
+            }
         }
     }
 
-    let active_mods = mod_list.active_mods();
-    debug!(
-        "{} mods active: {:?}",
-        active_mods.len(),
-        active_mods.keys().collect::<Vec<_>>()
-    );
-
-    let data = if let Some(path) = prototype_dump {
-        DataRaw::load(&path).change_context(ScannerError::SetupError)?
-    } else {
-        get_protodump(
-            factorio,
-            factorio_bin,
-            &mod_list,
-            (
-                bp_helper::get_used_startup_settings(bp).unwrap_or(&BTreeMap::new()),
-                bp.version,
-            ),
-        )?
-    };
-
-    info!("loaded prototype data");
-    Ok((DataUtil::new(data), active_mods))
+    res.push_str(rest);
+    res.trim().to_owned()
 }
 
-fn render(
-    raw_bp: &blueprint::Data,
-    data: &DataUtil,
-    used_mods: &UsedMods,
+/// Renders every blueprint of a book into its own file and writes a manifest of all pages.
+///
+/// The required mods are detected for every page on its own like for a single blueprint,
+/// pages that need the same mods are rendered with the same loaded data.
+#[allow(clippy::too_many_arguments, clippy::too_many_lines)]
+fn render_book(
+    bp: &blueprint::Data,
+    (factorio, factorio_bin): (&Path, &Path),
+    portal_cache: &PortalCache,
+    (preset, mods): (Option<preset::Preset>, &[String]),
+    prototype_dump: Option<&Path>,
     target_res: f64,
-) -> Result<(Vec<u8>, HashSet<String>, Option<Vec<u8>>), ScannerError> {
-    let bp = raw_bp
-        .as_blueprint()
-        .ok_or(report!(ScannerError::NoBlueprint))?;
+    out: &Path,
+    sheet: Option<contact_sheet::Layout>,
+    schedules: bool,
+) -> Result<(), ScannerError> {
+    let pages = bp.blueprints();
+    ensure!(!pages.is_empty(), ScannerError::NoBlueprint);
 
-    let size = calculate_target_size(bp, data, target_res, 0.5).ok_or(ScannerError::RenderError)?;
-    info!("target size: {size}");
+    info!("rendering {} blueprints", pages.len());
+
+    let mut manifest = pages
+        .iter()
+        .map(|(path, page)| BookManifestEntry {
+            index: path.last().copied(),
+            path: path.clone(),
+            label: page.label().to_owned(),
+            file: None,
+            thumbnail: None,
+            width: None,
+            height: None,
+            missing: Vec::new(),
+            error: None,
+        })
+        .collect::<Vec<_>>();
+    let mut sheet_pages = pages.iter().map(|_| None).collect::<Vec<_>>();
+
+    let mut groups = BTreeMap::<String, (Requirements, Vec<usize>)>::new();
+    for (idx, (_, page)) in pages.iter().enumerate() {
+        match Requirements::from_bp(page, preset, mods) {
+            Ok(requirements) => {
+                let key = requirements.key();
+                groups
+                    .entry(key)
+                    .or_insert_with(|| (requirements, Vec::new()))
+                    .1
+                    .push(idx);
+            }
+            Err(err) => {
+                warn!(
+                    "failed to detect the mods of page {:?}: {err:?}",
+                    manifest[idx].path
+                );
+                manifest[idx].error = Some(err.to_string());
+            }
+        }
+    }
 
+    // the pages of a book usually share most of their sprites
     let image_cache = &mut ImageCache::new();
-    let (img, unknown) = render_bp(
-        bp,
-        data,
-        used_mods,
-        RenderLayerBuffer::new(size),
-        image_cache,
-    )
-    .ok_or(ScannerError::RenderError)?;
-    info!("render completed");
+    let mut sheet_font = None;
 
-    let mut res = Vec::new();
-    let enc = png::PngEncoder::new_with_quality(
-        &mut res,
-        png::CompressionType::Best,
-        png::FilterType::default(),
-    );
+    for (requirements, indices) in groups.into_values() {
+        let (data, used_mods) = match load_requirements(
+            &requirements,
+            factorio,
+            factorio_bin,
+            portal_cache,
+            prototype_dump.map(Path::to_path_buf),
+            &log_progress,
+        ) {
+            Ok(res) => res,
+            Err(err) => {
+                warn!("failed to load data for {} pages: {err:?}", indices.len());
+                for idx in indices {
+                    manifest[idx].error = Some(err.to_string());
+                }
+                continue;
+            }
+        };
 
-    enc.write_image(img.as_bytes(), img.width(), img.height(), img.color())
-        .change_context(ScannerError::RenderError)?;
+        let schedule_font = if schedules {
+            let font = Font::load(&used_mods);
+            if font.is_none() {
+                warn!("no font available, skipping schedule sidebar");
+            }
 
-    let thumbnail = render_thumbnail(raw_bp, data, used_mods, image_cache).map(|t| {
-        let mut res = Vec::new();
-        let enc = png::PngEncoder::new_with_quality(
-            &mut res,
-            png::CompressionType::Best,
-            png::FilterType::default(),
-        );
+            font
+        } else {
+            None
+        };
 
-        let _ = enc.write_image(t.as_bytes(), t.width(), t.height(), t.color());
-        res
-    });
+        if sheet.is_some() && sheet_font.is_none() {
+            sheet_font = Font::load(&used_mods);
+        }
 
-    Ok((res, unknown, thumbnail))
-}
+        for idx in indices {
+            let (path, page) = &pages[idx];
+            let entry = &mut manifest[idx];
+            let file = book_page_path(out, path);
 
-#[cfg(feature = "server")]
-use server::api_capnp;
+            let (img, missing, thumb) = match render_image(
+                page,
+                &data,
+                &used_mods,
+                target_res,
+                image_cache,
+                &log_progress,
+                &AtomicBool::new(false),
+            ) {
+                Ok(res) => res,
+                Err(err) => {
+                    warn!("failed to render {file:?}: {err:?}");
+                    entry.error = Some(err.to_string());
+                    continue;
+                }
+            };
+
+            let mut missing = missing.into_iter().collect::<Vec<_>>();
+            missing.sort();
+
+            if !missing.is_empty() {
+                warn!("missing prototypes in {file:?}: {missing:?}");
+            }
+
+            let img = match (&schedule_font, page.as_blueprint()) {
+                (Some(font), Some(bp)) => schedules::attach(img, &bp.schedules, font),
+                _ => img,
+            };
+
+            fs::write(&file, encode_png(&img)?).change_context(ScannerError::RenderError)?;
+            info!("saved render to {file:?}");
+
+            if let Some(thumb) = &thumb {
+                let thumb_file = file.with_extension("thumb.png");
+                fs::write(&thumb_file, encode_png(thumb)?)
+                    .change_context(ScannerError::RenderError)?;
+                entry.thumbnail = Some(thumb_file);
+            }
+
+            if let Some(layout) = sheet {
+                sheet_pages[idx] = Some(contact_sheet::Page {
+                    thumbnail: thumb,
+                    render: layout.fit_render(&img),
+                    label: strip_rich_text(page.label()),
+                    entities: page.as_blueprint().map_or(0, |bp| bp.entities.len()),
+                });
+            }
+
+            entry.file = Some(file);
+            entry.width = Some(img.width());
+            entry.height = Some(img.height());
+            entry.missing = missing;
+        }
+    }
+
+    let manifest_path = out.with_extension("manifest.json");
+    fs::write(
+        &manifest_path,
+        serde_json::to_vec_pretty(&manifest).change_context(ScannerError::RenderError)?,
+    )
+    .change_context(ScannerError::RenderError)?;
+    info!("saved manifest to {manifest_path:?}");
+
+    if let Some(layout) = sheet {
+        let sheet_pages = sheet_pages.into_iter().flatten().collect::<Vec<_>>();
+        let Some(sheet) = contact_sheet::compose(&sheet_pages, layout, sheet_font.as_ref()) else {
+            warn!("no blueprint of the book could be rendered, skipping contact sheet");
+            return Ok(());
+        };
+
+        let sheet_path = out.with_extension("sheet.png");
+        fs::write(&sheet_path, encode_png(&sheet)?).change_context(ScannerError::RenderError)?;
+        info!("saved contact sheet to {sheet_path:?}");
+    }
+
+    Ok(())
+}
+
+/// Mods and startup settings that are needed to render a blueprint.
+struct Requirements {
+    mods: DependencyList,
+    settings: BTreeMap<String, AnyBasic>,
+    version: u64,
+}
+
+impl Requirements {
+    fn from_bp(
+        bp: &blueprint::Data,
+        preset: Option<preset::Preset>,
+        mods: &[String],
+    ) -> Result<Self, ScannerError> {
+        let blueprint = bp.as_blueprint();
+        ensure!(
+            blueprint.is_some() || bp.is_planner(),
+            ScannerError::NoBlueprint
+        );
+
+        // get used mods from preset or detect from BP meta info
+        let mut required_mods = std::iter::once((
+            "base".to_owned(),
+            DependencyVersion::Exact(prototypes::targeted_engine_version()),
+        ))
+        .collect::<HashMap<_, _>>();
+        if let Some(preset) = preset {
+            required_mods.extend(preset.used_mods());
+        } else {
+            if let Some(blueprint) = blueprint {
+                required_mods.extend(bp_helper::get_used_versions(blueprint));
+            }
+
+            required_mods.extend(bp_helper::get_planner_used_versions(bp));
+        }
+        required_mods.extend(mods.iter().map(|m| (m.clone(), DependencyVersion::Any)));
+
+        let (settings, version) = blueprint
+            .and_then(|bp| {
+                bp_helper::get_used_startup_settings(bp).map(|s| (s.clone(), bp.version))
+            })
+            .unwrap_or_else(|| (BTreeMap::new(), bp.version()));
+
+        Ok(Self {
+            mods: required_mods,
+            settings,
+            version,
+        })
+    }
+
+    /// Blueprints with the same key can be rendered with the same loaded data.
+    fn key(&self) -> String {
+        let mut mods = self
+            .mods
+            .iter()
+            .map(|(n, v)| format!("{n} {v}"))
+            .collect::<Vec<_>>();
+        mods.sort();
+
+        let settings = self
+            .settings
+            .iter()
+            .map(|(k, v)| format!("{k}={v}"))
+            .collect::<Vec<_>>();
+
+        format!("{}|{}", mods.join(","), settings.join(","))
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn load_data(
+    bp: &blueprint::Data,
+    factorio: &Path,
+    factorio_bin: &Path,
+    portal_cache: &PortalCache,
+    preset: Option<preset::Preset>,
+    mods: &[String],
+    prototype_dump: Option<PathBuf>,
+    progress: &dyn Fn(&Progress),
+) -> Result<(DataUtil, UsedMods), ScannerError> {
+    let requirements = Requirements::from_bp(bp, preset, mods)?;
+    info!("loaded BP");
+
+    load_requirements(
+        &requirements,
+        factorio,
+        factorio_bin,
+        portal_cache,
+        prototype_dump,
+        progress,
+    )
+}
+
+fn load_requirements(
+    requirements: &Requirements,
+    factorio: &Path,
+    factorio_bin: &Path,
+    portal_cache: &PortalCache,
+    prototype_dump: Option<PathBuf>,
+    progress: &dyn Fn(&Progress),
+) -> Result<(DataUtil, UsedMods), ScannerError> {
+    let (mod_list, active_mods) = prepare_mods(requirements, factorio, portal_cache, progress)?;
+    let key = DumpKey::new(&active_mods, &requirements.settings);
+
+    load_prepared(
+        requirements,
+        &mod_list,
+        active_mods,
+        key,
+        (factorio, factorio_bin),
+        prototype_dump,
+        progress,
+    )
+}
+
+/// Enables the required mods (downloading missing ones) and returns the mod list together with the active mods.
+fn prepare_mods<'a>(
+    requirements: &Requirements,
+    factorio: &'a Path,
+    portal_cache: &PortalCache,
+    progress: &dyn Fn(&Progress),
+) -> Result<(ModList<'a>, UsedMods), ScannerError> {
+    let mut mod_list = ModList::generate(factorio).change_context(ScannerError::SetupError)?;
+    let required_mods = &requirements.mods;
+
+    debug!(
+        "required mods: {}",
+        required_mods
+            .iter()
+            .map(|(n, v)| format!("{n} {v}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    if !required_mods.is_empty() {
+        debug!("checking mod dependencies");
+        progress(&Progress::ResolvingMods);
+
+        mod_list.load_local_dependency_info(required_mods);
+        let used_mods = resolve_mod_dependencies(required_mods, &mut mod_list, portal_cache)
+            .change_context(ScannerError::SetupError)?;
+
+        let missing = mod_list.enable_mods(&used_mods);
+        if missing.is_empty() {
+            debug!("all mods are already installed");
+        } else if portal_cache.is_offline() {
+            return Err(report!(ScannerError::SetupError)).attach_printable(format!(
+                "mods are missing and can't be downloaded while offline: {missing:?}"
+            ));
+        } else {
+            info!("downloading missing mods from mod portal");
+            download_mods(missing, factorio, progress).change_context(ScannerError::SetupError)?;
+        }
+    }
+
+    let active_mods = mod_list.active_mods();
+    debug!(
+        "{} mods active: {:?}",
+        active_mods.len(),
+        active_mods.keys().collect::<Vec<_>>()
+    );
+
+    Ok((mod_list, active_mods))
+}
+
+fn load_prepared(
+    requirements: &Requirements,
+    mod_list: &ModList,
+    active_mods: UsedMods,
+    key: DumpKey,
+    (factorio, factorio_bin): (&Path, &Path),
+    prototype_dump: Option<PathBuf>,
+    progress: &dyn Fn(&Progress),
+) -> Result<(DataUtil, UsedMods), ScannerError> {
+    let data = if let Some(path) = prototype_dump {
+        DataRaw::load(&path).change_context(ScannerError::SetupError)?
+    } else {
+        get_protodump(
+            factorio,
+            factorio_bin,
+            mod_list,
+            key,
+            (&requirements.settings, requirements.version),
+            progress,
+        )?
+    };
+
+    info!("loaded prototype data");
+    Ok((DataUtil::new(data), active_mods))
+}
+
+fn render(
+    raw_bp: &blueprint::Data,
+    data: &DataUtil,
+    used_mods: &UsedMods,
+    target_res: f64,
+    image_cache: &mut ImageCache,
+    progress: &dyn Fn(&Progress),
+    cancelled: &AtomicBool,
+) -> Result<(Vec<u8>, HashSet<String>, Option<Vec<u8>>), ScannerError> {
+    let (img, unknown, thumbnail) = render_image(
+        raw_bp,
+        data,
+        used_mods,
+        target_res,
+        image_cache,
+        progress,
+        cancelled,
+    )?;
+
+    let res = encode_png(&img)?;
+    let thumbnail = thumbnail.and_then(|t| encode_png(&t).ok());
+
+    Ok((res, unknown, thumbnail))
+}
+
+fn render_image(
+    raw_bp: &blueprint::Data,
+    data: &DataUtil,
+    used_mods: &UsedMods,
+    target_res: f64,
+    image_cache: &mut ImageCache,
+    progress: &dyn Fn(&Progress),
+    cancelled: &AtomicBool,
+) -> Result<
+    (
+        image::DynamicImage,
+        HashSet<String>,
+        Option<image::DynamicImage>,
+    ),
+    ScannerError,
+> {
+    let (img, unknown) = if raw_bp.is_planner() {
+        planner_card::render(raw_bp, data, used_mods, image_cache)
+            .ok_or(ScannerError::RenderError)?
+    } else {
+        let bp = raw_bp
+            .as_blueprint()
+            .ok_or(report!(ScannerError::NoBlueprint))?;
+
+        let size =
+            calculate_target_size(bp, data, target_res, 0.5).ok_or(ScannerError::RenderError)?;
+        info!("target size: {size}");
+
+        let mut render_layers = RenderLayerBuffer::new(size);
+        let unknown = render_bp(
+            bp,
+            data,
+            used_mods,
+            &mut render_layers,
+            image_cache,
+            progress,
+            cancelled,
+        )
+        .ok_or(ScannerError::RenderError)?;
+
+        (render_layers.combine(), unknown)
+    };
+    info!("render completed");
+
+    let thumbnail = render_thumbnail(raw_bp, data, used_mods, image_cache, THUMBNAIL_SIZE);
+
+    Ok((img, unknown, thumbnail))
+}
+
+/// Renders a blueprint as SVG document, see [`prototypes::svg::SvgRecorder`].
+fn render_svg(
+    raw_bp: &blueprint::Data,
+    data: &DataUtil,
+    used_mods: &UsedMods,
+    target_res: f64,
+    image_cache: &mut ImageCache,
+    progress: &dyn Fn(&Progress),
+) -> Result<(String, HashSet<String>), ScannerError> {
+    let bp = raw_bp
+        .as_blueprint()
+        .ok_or_else(|| report!(ScannerError::NoBlueprint))
+        .attach_printable("only blueprints can be rendered as SVG")?;
+
+    let size = calculate_target_size(bp, data, target_res, 0.5).ok_or(ScannerError::RenderError)?;
+    info!("target size: {size}");
+
+    let mut render_layers = RenderLayerBuffer::new(size).with_svg();
+    let unknown = render_bp(
+        bp,
+        data,
+        used_mods,
+        &mut render_layers,
+        image_cache,
+        progress,
+        &AtomicBool::new(false),
+    )
+    .ok_or(ScannerError::RenderError)?;
+    info!("render completed");
+
+    let svg = render_layers.to_svg().ok_or(ScannerError::RenderError)?;
+
+    Ok((svg, unknown))
+}
+
+fn encode_png(img: &image::DynamicImage) -> Result<Vec<u8>, ScannerError> {
+    let mut res = Vec::new();
+    let enc = png::PngEncoder::new_with_quality(
+        &mut res,
+        png::CompressionType::Best,
+        png::FilterType::default(),
+    );
+
+    enc.write_image(img.as_bytes(), img.width(), img.height(), img.color())
+        .change_context(ScannerError::RenderError)?;
+
+    Ok(res)
+}
+
+#[cfg(feature = "server")]
+use server::api_capnp;
 
 #[cfg(feature = "server")]
 pub mod server {
     #[allow(clippy::wildcard_imports)]
     use super::*;
-    use std::sync::Arc;
-
-    use actix::{
-        ActorFutureExt, AsyncContext, Handler, Message, ResponseActFuture, StreamHandler,
-        WrapFuture,
+    use std::{
+        collections::VecDeque,
+        sync::{
+            atomic::{AtomicBool, AtomicU64, Ordering},
+            Arc,
+        },
+        time::Duration,
     };
+
+    use actix::{AsyncContext, Handler, Message, ResponseActFuture, StreamHandler, WrapFuture};
     use actix_web::{
-        get,
+        get, post,
         web::{self, Buf, Bytes},
-        App, HttpRequest, HttpResponse, HttpServer, Responder,
+        App, HttpMessage, HttpRequest, HttpResponse, HttpServer, Responder,
     };
     use actix_web_actors::ws;
+    use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
     use capnp::{
         message::{Builder, ReaderOptions},
         serialize,
@@ -554,6 +1350,7 @@ pub mod server {
         include!(concat!(env!("OUT_DIR"), "/schemas/api_capnp.rs"));
     }
 
+    #[derive(Debug, PartialEq, Eq)]
     pub enum ApiRequest {
         Quit {
             id: u64,
@@ -572,6 +1369,7 @@ pub mod server {
             bp_string: String,
             preset: String,
             mods: Vec<String>,
+            size: u16,
         },
     }
 
@@ -611,11 +1409,12 @@ pub mod server {
                         .filter_map(|m| m.ok()?.to_string().ok())
                         .collect();
 
-                    Some(Self::RenderBP {
+                    Some(Self::RenderThumbnail {
                         id,
                         bp_string,
                         preset,
                         mods,
+                        size: r.get_size(),
                     })
                 }
             }
@@ -644,23 +1443,124 @@ pub mod server {
 
     type ReturnChannel = oneshot::Sender<(Vec<u8>, bool)>;
 
+    /// Serialized progress responses of a request, sent before its final response.
+    type ProgressChannel = mpsc::UnboundedSender<Vec<u8>>;
+
+    type QueueItem = (ApiRequest, ReturnChannel, Option<ProgressChannel>);
+
     struct ServerData {
-        input: mpsc::Sender<(ApiRequest, ReturnChannel)>,
+        input: mpsc::Sender<QueueItem>,
     }
 
-    #[allow(clippy::too_many_lines)]
+    /// Prototype data & mods of a loaded mod set, shared between the workers.
+    type LoadedData = Arc<(DataUtil, UsedMods)>;
+
+    struct Shared {
+        factorio: PathBuf,
+        factorio_bin: PathBuf,
+        portal_cache: PortalCache,
+        image_cache: ImageCache,
+
+        /// Only locked to look up or insert a data set, workers with loaded data never wait for a load.
+        loaded: std::sync::Mutex<DataSets>,
+
+        /// Loading modifies the mod list & settings of the factorio installation,
+        /// so this lock is held while loading to prevent concurrent loads.
+        loading: std::sync::Mutex<()>,
+    }
+
+    impl Shared {
+        fn data_sets(&self) -> std::sync::MutexGuard<'_, DataSets> {
+            self.loaded
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+        }
+    }
+
+    struct DataSet {
+        key: DumpKey,
+
+        /// [`Requirements::key`]s that resolved to this data set, allows skipping the mod resolution
+        requirements: HashSet<String>,
+
+        data: LoadedData,
+    }
+
+    /// LRU of the loaded data sets, most recently used first.
+    struct DataSets {
+        capacity: usize,
+        sets: VecDeque<DataSet>,
+    }
+
+    impl DataSets {
+        fn new(capacity: usize) -> Self {
+            Self {
+                capacity: capacity.max(1),
+                sets: VecDeque::new(),
+            }
+        }
+
+        fn take(&mut self, pred: impl Fn(&DataSet) -> bool) -> Option<&mut DataSet> {
+            let idx = self.sets.iter().position(pred)?;
+            let set = self.sets.remove(idx)?;
+            self.sets.push_front(set);
+            self.sets.front_mut()
+        }
+
+        fn get_by_requirements(&mut self, requirements: &str) -> Option<LoadedData> {
+            self.take(|s| s.requirements.contains(requirements))
+                .map(|s| s.data.clone())
+        }
+
+        fn get(&mut self, key: DumpKey, requirements: String) -> Option<LoadedData> {
+            let set = self.take(|s| s.key == key)?;
+            set.requirements.insert(requirements);
+            Some(set.data.clone())
+        }
+
+        fn insert(&mut self, key: DumpKey, requirements: String, data: LoadedData) {
+            while self.sets.len() >= self.capacity {
+                if let Some(evicted) = self.sets.pop_back() {
+                    info!("unloading prototype data {}", evicted.key);
+                }
+            }
+
+            self.sets.push_front(DataSet {
+                key,
+                requirements: std::iter::once(requirements).collect(),
+                data,
+            });
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn run(
         factorio: &Path,
         factorio_bin: &Path,
+        portal_cache: PortalCache,
         address: IpAddr,
         port: u16,
         max_queue: usize,
+        workers: usize,
+        timeout: Duration,
+        data_sets: usize,
+        image_cache: ImageCache,
     ) -> Result<(), Error> {
         info!("starting server on {address}:{port}");
 
-        let (input_tx, mut input_rx) = mpsc::channel(max_queue);
+        let (input_tx, input_rx) = mpsc::channel(max_queue);
         let server_data = web::Data::new(Arc::new(Mutex::new(ServerData { input: input_tx })));
 
+        let input_rx = Arc::new(Mutex::new(input_rx));
+        let shared = Arc::new(Shared {
+            factorio: factorio.to_owned(),
+            factorio_bin: factorio_bin.to_owned(),
+            portal_cache,
+            image_cache,
+            loaded: std::sync::Mutex::new(DataSets::new(data_sets)),
+            loading: std::sync::Mutex::new(()),
+        });
+
         let rt = tokio::runtime::Builder::new_multi_thread()
             .enable_all()
             .build()
@@ -674,6 +1574,8 @@ pub mod server {
                             .app_data(server_data.clone())
                             .service(index)
                             .service(ws_entry)
+                            .service(http_presets)
+                            .service(http_render)
                     })
                     .bind((address, port))
                     .change_context(Error)?
@@ -683,175 +1585,346 @@ pub mod server {
                 }
             };
 
-            let factorio = factorio.to_owned();
-            let factorio_bin = factorio_bin.to_owned();
-
             let processor = {
                 async move {
-                    info!("ready to process requests");
-                    loop {
-                        let Some((req, res_tx)) = input_rx.recv().await else {
-                            continue;
-                        };
+                    let workers = workers.max(1);
+                    info!("ready to process requests with {workers} workers");
 
-                        let id = req.get_id();
+                    futures_util::future::join_all(
+                        (0..workers).map(|_| worker(input_rx.clone(), shared.clone(), timeout)),
+                    )
+                    .await;
+                }
+            };
 
-                        let mut message = Builder::new_default();
-                        let mut response = message.init_root::<api_capnp::response::Builder>();
-                        response.set_id(id);
+            pin_utils::pin_mut!(server, processor);
+            futures_util::future::select(server, processor).await;
+        });
 
-                        match &req {
-                            ApiRequest::Quit { .. } => {}
-                            ApiRequest::GetPresets { .. } => {
-                                let mut p = Vec::new();
+        Ok(())
 
-                                for preset in preset::Preset::iter() {
-                                    p.push(preset.to_string());
-                                }
+        // match res {
+        //     None => Err(ServerError).attach_printable("server exited unexpectedly"),
+        //     Some(Err(err)) => Err(err)
+        //         .change_context(ServerError)
+        //         .attach_printable("unexpected server process error"),
+        //     Some(Ok(Err(err))) => Err(err),
+        //     Some(Ok(Ok(()))) => Ok(()),
+        // }
+    }
 
-                                if let Err(err) = response.set_presets(p.as_slice()) {
-                                    error!("{err:?}");
-                                    response.set_request_error(
-                                        api_capnp::response::ErrorType::ProcessingError,
-                                    );
-                                }
-                            }
-                            ApiRequest::RenderBP {
-                                bp_string,
-                                preset,
-                                mods,
-                                ..
-                            } => 'render: {
-                                let bp = match blueprint::Data::try_from(bp_string.as_str()) {
-                                    Ok(bp) => bp,
-                                    Err(err) => {
-                                        warn!("{err:?}");
-                                        response.set_request_error(
-                                            api_capnp::response::ErrorType::ProcessingError,
-                                        );
-                                        break 'render;
-                                    }
-                                };
+    /// Pulls requests from the queue and processes them on a blocking thread.
+    ///
+    /// Responses are sent as soon as they are done, clients match them to their requests by id.
+    async fn worker(
+        input: Arc<Mutex<mpsc::Receiver<QueueItem>>>,
+        shared: Arc<Shared>,
+        timeout: Duration,
+    ) {
+        loop {
+            let Some((req, mut res_tx, progress_tx)) = input.lock().await.recv().await else {
+                break;
+            };
 
-                                let (data, used_mods) = match load_data(
-                                    &bp,
-                                    &factorio,
-                                    &factorio_bin,
-                                    preset.parse().ok(),
-                                    mods,
-                                    None,
-                                ) {
-                                    Ok(d) => d,
-                                    Err(err) => {
-                                        error!("{err:?}");
-                                        response.set_request_error(
-                                            api_capnp::response::ErrorType::ProcessingError,
-                                        );
-                                        break 'render;
-                                    }
-                                };
+            let id = req.get_id();
+            if res_tx.is_closed() {
+                debug!("request {id} was cancelled before processing");
+                continue;
+            }
 
-                                match render(&bp, &data, &used_mods, 2048.0) {
-                                    Ok((img, missing, thumbnail)) => {
-                                        let mut rendered = response.init_rendered_bp();
-                                        rendered.set_image(&img);
+            let close = matches!(req, ApiRequest::Quit { .. });
+            let mut job = BlockingJob::spawn({
+                let shared = shared.clone();
+                move |cancelled: &AtomicBool| {
+                    let progress = |progress: &Progress| {
+                        if let Some(progress_tx) = &progress_tx {
+                            progress_tx.send(progress_message(id, progress)).ok();
+                        }
+                    };
 
-                                        if let Err(err) = rendered.set_missing(
-                                            missing.iter().collect::<Vec<_>>().as_slice(),
-                                        ) {
-                                            error!("{err:?}");
-                                            //response.set_request_error(api_capnp::response::ErrorType::ProcessingError);
-                                        }
+                    process(&req, &shared, cancelled, &progress)
+                }
+            });
 
-                                        if let Some(thumbnail) = thumbnail {
-                                            rendered.set_thumbnail(&thumbnail);
-                                        }
-                                    }
-                                    Err(err) => {
-                                        error!("{err:?}");
-                                        response.set_request_error(
-                                            api_capnp::response::ErrorType::ProcessingError,
-                                        );
-                                    }
-                                }
-                            }
-                            ApiRequest::RenderThumbnail {
-                                bp_string,
-                                preset,
-                                mods,
-                                ..
-                            } => 'render: {
-                                let bp = match blueprint::Data::try_from(bp_string.as_str()) {
-                                    Ok(bp) => bp,
-                                    Err(err) => {
-                                        warn!("{err:?}");
-                                        response.set_request_error(
-                                            api_capnp::response::ErrorType::ProcessingError,
-                                        );
-                                        break 'render;
-                                    }
-                                };
+            let res = match job.wait(timeout, res_tx.closed()).await {
+                JobOutcome::Done(res) => Some(res.unwrap_or_else(|err| {
+                    error!("processing of request {id} failed: {err:?}");
+                    error_message(id, api_capnp::response::ErrorType::ProcessingError)
+                })),
+                JobOutcome::TimedOut => {
+                    warn!("request {id} timed out after {timeout:?}");
+                    Some(error_message(id, api_capnp::response::ErrorType::Timeout))
+                }
+                JobOutcome::Closed => {
+                    debug!("request {id} was cancelled");
+                    None
+                }
+            };
 
-                                let (data, used_mods) = match load_data(
-                                    &bp,
-                                    &factorio,
-                                    &factorio_bin,
-                                    preset.parse().ok(),
-                                    mods,
-                                    None,
-                                ) {
-                                    Ok(d) => d,
-                                    Err(err) => {
-                                        error!("{err:?}");
-                                        response.set_request_error(
-                                            api_capnp::response::ErrorType::ProcessingError,
-                                        );
-                                        break 'render;
-                                    }
-                                };
+            if let Some(res) = res {
+                if res_tx.send((res, close)).is_err() {
+                    debug!("failed to send result for {id} back to the client");
+                }
+            }
 
-                                if let Some(thumbnail) =
-                                    render_thumbnail(&bp, &data, &used_mods, &mut ImageCache::new())
-                                {
-                                    response
-                                        .init_rendered_thumbnail()
-                                        .set_image(thumbnail.as_bytes());
-                                };
-                            }
-                        };
+            job.stop().await;
+        }
+    }
+
+    enum JobOutcome<T> {
+        Done(std::result::Result<T, tokio::task::JoinError>),
+        TimedOut,
+        Closed,
+    }
+
+    /// Work on a blocking thread that can be asked to stop early.
+    struct BlockingJob<T> {
+        task: tokio::task::JoinHandle<T>,
+        cancelled: Arc<AtomicBool>,
+    }
+
+    impl<T: Send + 'static> BlockingJob<T> {
+        fn spawn(job: impl FnOnce(&AtomicBool) -> T + Send + 'static) -> Self {
+            let cancelled = Arc::new(AtomicBool::new(false));
+            let task = tokio::task::spawn_blocking({
+                let cancelled = cancelled.clone();
+                move || job(&cancelled)
+            });
+
+            Self { task, cancelled }
+        }
+
+        /// Waits until the job is done, `timeout` passed or `closed` completed.
+        async fn wait(
+            &mut self,
+            timeout: Duration,
+            closed: impl std::future::Future<Output = ()> + Send,
+        ) -> JobOutcome<T> {
+            tokio::select! {
+                res = &mut self.task => JobOutcome::Done(res),
+                () = tokio::time::sleep(timeout) => JobOutcome::TimedOut,
+                () = closed => JobOutcome::Closed,
+            }
+        }
+
+        /// Asks an unfinished job to stop and waits until it did.
+        ///
+        /// The blocking thread can't be killed, it stops at the next cancellation check.
+        /// Waiting for it keeps the number of busy threads limited to the worker count.
+        async fn stop(self) {
+            if !self.task.is_finished() {
+                self.cancelled.store(true, Ordering::Relaxed);
+                self.task.await.ok();
+            }
+        }
+    }
+
+    fn load_shared(
+        shared: &Shared,
+        bp: &blueprint::Data,
+        preset: &str,
+        mods: &[String],
+        progress: &dyn Fn(&Progress),
+    ) -> Result<LoadedData, ScannerError> {
+        let requirements = Requirements::from_bp(bp, preset.parse().ok(), mods)?;
+        let requirements_key = requirements.key();
+
+        let cached = shared.data_sets().get_by_requirements(&requirements_key);
+        if let Some(data) = cached {
+            return Ok(data);
+        }
+
+        let loading = shared
+            .loading
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+
+        // another worker might have loaded the same data while this one was waiting
+        let cached = shared.data_sets().get_by_requirements(&requirements_key);
+        if let Some(data) = cached {
+            return Ok(data);
+        }
+
+        // different requirements can resolve to the same mods, e.g. a preset and the meta info of a blueprint
+        let (mod_list, active_mods) = prepare_mods(
+            &requirements,
+            &shared.factorio,
+            &shared.portal_cache,
+            progress,
+        )?;
+        let key = DumpKey::new(&active_mods, &requirements.settings);
+
+        let cached = shared.data_sets().get(key, requirements_key.clone());
+        if let Some(data) = cached {
+            return Ok(data);
+        }
+
+        info!("loading prototype data {key}");
+        let data = Arc::new(load_prepared(
+            &requirements,
+            &mod_list,
+            active_mods,
+            key,
+            (&shared.factorio, &shared.factorio_bin),
+            None,
+            progress,
+        )?);
+
+        // inserted before releasing the load lock so that waiting workers find it
+        shared
+            .data_sets()
+            .insert(key, requirements_key, data.clone());
+        drop(loading);
+
+        Ok(data)
+    }
+
+    /// Processes a single request and returns the serialized response.
+    #[allow(clippy::too_many_lines)]
+    fn process(
+        req: &ApiRequest,
+        shared: &Shared,
+        cancelled: &AtomicBool,
+        progress: &dyn Fn(&Progress),
+    ) -> Vec<u8> {
+        let id = req.get_id();
+        let mut image_cache = shared.image_cache.clone();
+
+        let mut message = Builder::new_default();
+        let mut response = message.init_root::<api_capnp::response::Builder>();
+        response.set_id(id);
+
+        match req {
+            ApiRequest::Quit { .. } => {}
+            ApiRequest::GetPresets { .. } => {
+                let mut p = Vec::new();
+
+                for preset in preset::Preset::iter() {
+                    p.push(preset.to_string());
+                }
+
+                if let Err(err) = response.set_presets(p.as_slice()) {
+                    error!("{err:?}");
+                    response.set_request_error(api_capnp::response::ErrorType::ProcessingError);
+                }
+            }
+            ApiRequest::RenderBP {
+                bp_string,
+                preset,
+                mods,
+                ..
+            } => 'render: {
+                let bp = match blueprint::Data::try_from(bp_string.as_str()) {
+                    Ok(bp) => bp,
+                    Err(err) => {
+                        warn!("{err:?}");
+                        response.set_request_error(api_capnp::response::ErrorType::ProcessingError);
+                        break 'render;
+                    }
+                };
+
+                let data = match load_shared(shared, &bp, preset, mods, progress) {
+                    Ok(d) => d,
+                    Err(err) => {
+                        error!("{err:?}");
+                        response.set_request_error(api_capnp::response::ErrorType::ProcessingError);
+                        break 'render;
+                    }
+                };
 
-                        if let Err(err) = res_tx
-                            .send((
-                                serialize::write_message_segments_to_words(&message),
-                                matches!(req, ApiRequest::Quit { .. }),
-                            ))
-                            .map_err(|err| {
-                                report!(Error).attach_printable(format!(
-                                    "failed to send result for {id} back to websocket handler"
-                                ))
-                            })
+                if cancelled.load(Ordering::Relaxed) {
+                    debug!("skipping render of cancelled request {id}");
+                    break 'render;
+                }
+
+                let (data, used_mods) = &*data;
+                match render(
+                    &bp,
+                    data,
+                    used_mods,
+                    2048.0,
+                    &mut image_cache,
+                    progress,
+                    cancelled,
+                ) {
+                    Ok((img, missing, thumbnail)) => {
+                        let mut rendered = response.init_rendered_bp();
+                        rendered.set_image(&img);
+
+                        if let Err(err) =
+                            rendered.set_missing(missing.iter().collect::<Vec<_>>().as_slice())
                         {
                             error!("{err:?}");
-                            return;
-                        };
+                            //response.set_request_error(api_capnp::response::ErrorType::ProcessingError);
+                        }
+
+                        if let Some(thumbnail) = thumbnail {
+                            rendered.set_thumbnail(&thumbnail);
+                        }
+                    }
+                    Err(err) => {
+                        error!("{err:?}");
+                        response.set_request_error(api_capnp::response::ErrorType::ProcessingError);
                     }
                 }
-            };
+            }
+            ApiRequest::RenderThumbnail {
+                bp_string,
+                preset,
+                mods,
+                size,
+                ..
+            } => 'render: {
+                let bp = match blueprint::Data::try_from(bp_string.as_str()) {
+                    Ok(bp) => bp,
+                    Err(err) => {
+                        warn!("{err:?}");
+                        response.set_request_error(api_capnp::response::ErrorType::ProcessingError);
+                        break 'render;
+                    }
+                };
+
+                let data = match load_shared(shared, &bp, preset, mods, progress) {
+                    Ok(d) => d,
+                    Err(err) => {
+                        error!("{err:?}");
+                        response.set_request_error(api_capnp::response::ErrorType::ProcessingError);
+                        break 'render;
+                    }
+                };
 
-            pin_utils::pin_mut!(server, processor);
-            futures_util::future::select(server, processor).await;
-        });
+                if cancelled.load(Ordering::Relaxed) {
+                    debug!("skipping thumbnail of cancelled request {id}");
+                    break 'render;
+                }
 
-        Ok(())
+                let size = if *size == 0 {
+                    THUMBNAIL_SIZE
+                } else {
+                    u32::from(*size)
+                };
+
+                // fast path: only the icons are rendered, not the blueprint itself
+                let (data, used_mods) = &*data;
+                match render_thumbnail(&bp, data, used_mods, &mut image_cache, size)
+                    .map(|thumbnail| encode_png(&thumbnail))
+                {
+                    Some(Ok(thumbnail)) => {
+                        response.init_rendered_thumbnail().set_image(&thumbnail);
+                    }
+                    Some(Err(err)) => {
+                        error!("{err:?}");
+                        response.set_request_error(api_capnp::response::ErrorType::ProcessingError);
+                    }
+                    None => {
+                        warn!("failed to render thumbnail");
+                        response.set_request_error(api_capnp::response::ErrorType::ProcessingError);
+                    }
+                }
+            }
+        };
 
-        // match res {
-        //     None => Err(ServerError).attach_printable("server exited unexpectedly"),
-        //     Some(Err(err)) => Err(err)
-        //         .change_context(ServerError)
-        //         .attach_printable("unexpected server process error"),
-        //     Some(Ok(Err(err))) => Err(err),
-        //     Some(Ok(Ok(()))) => Ok(()),
-        // }
+        serialize::write_message_segments_to_words(&message)
     }
 
     #[get("/")]
@@ -874,6 +1947,274 @@ pub mod server {
             .start()
     }
 
+    /// Ids for requests coming from the HTTP endpoints, they are only used for logging.
+    static NEXT_HTTP_ID: AtomicU64 = AtomicU64::new(0);
+
+    #[get("/presets")]
+    async fn http_presets() -> impl Responder {
+        HttpResponse::Ok().json(
+            preset::Preset::iter()
+                .map(|p| p.to_string())
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    #[derive(Debug, Default, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    enum RenderFormat {
+        #[default]
+        Png,
+        Json,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct RenderQuery {
+        #[serde(default)]
+        format: RenderFormat,
+
+        #[serde(default)]
+        preset: String,
+
+        /// comma separated list of additional mods
+        #[serde(default)]
+        mods: String,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct RenderBody {
+        bp_string: String,
+
+        #[serde(default)]
+        preset: String,
+
+        #[serde(default)]
+        mods: Vec<String>,
+    }
+
+    #[derive(Debug, Serialize)]
+    struct RenderResult {
+        image: String,
+        thumbnail: Option<String>,
+        missing: Vec<String>,
+    }
+
+    /// Renders a blueprint, the body is either the plain blueprint string (preset & mods as query
+    /// parameters) or a JSON object with `bp_string`, `preset` and `mods`.
+    ///
+    /// Responds with the PNG and the missing prototypes in the `X-Missing-Prototypes` header or
+    /// with a JSON object containing the base64 encoded images when `?format=json` is used.
+    #[post("/render")]
+    async fn http_render(
+        req: HttpRequest,
+        body: String,
+        query: web::Query<RenderQuery>,
+        data: web::Data<Arc<Mutex<ServerData>>>,
+    ) -> HttpResponse {
+        let query = query.into_inner();
+        let (bp_string, preset, mods) = if req.content_type() == "application/json" {
+            match serde_json::from_str::<RenderBody>(&body) {
+                Ok(body) => (body.bp_string, body.preset, body.mods),
+                Err(err) => {
+                    return HttpResponse::BadRequest().body(format!("invalid request body: {err}"))
+                }
+            }
+        } else {
+            let mods = query
+                .mods
+                .split(',')
+                .map(str::trim)
+                .filter(|m| !m.is_empty())
+                .map(ToOwned::to_owned)
+                .collect();
+
+            (body.trim().to_owned(), query.preset, mods)
+        };
+
+        if bp_string.is_empty() {
+            return HttpResponse::BadRequest().body("missing blueprint string");
+        }
+
+        let req = ApiRequest::RenderBP {
+            id: NEXT_HTTP_ID.fetch_add(1, Ordering::Relaxed),
+            bp_string,
+            preset,
+            mods,
+        };
+
+        let msg = match enqueue(&data, req, None).await {
+            Ok((msg, _)) => msg,
+            Err(QueueError::Full) => {
+                return HttpResponse::ServiceUnavailable().body("queue is full");
+            }
+            Err(QueueError::Failed) => {
+                return HttpResponse::InternalServerError().body("processing error");
+            }
+        };
+
+        let (image, missing, thumbnail) = match decode_rendered_bp(&msg) {
+            Ok(res) => res,
+            Err(
+                api_capnp::response::ErrorType::InvalidRequest
+                | api_capnp::response::ErrorType::InvalidPreset,
+            ) => return HttpResponse::BadRequest().body("invalid request"),
+            Err(api_capnp::response::ErrorType::QueueFull) => {
+                return HttpResponse::ServiceUnavailable().body("queue is full")
+            }
+            Err(api_capnp::response::ErrorType::Timeout) => {
+                return HttpResponse::GatewayTimeout().body("render timed out")
+            }
+            Err(api_capnp::response::ErrorType::ProcessingError) => {
+                return HttpResponse::InternalServerError().body("processing error")
+            }
+        };
+
+        match query.format {
+            RenderFormat::Png => {
+                let mut res = HttpResponse::Ok();
+                res.content_type("image/png");
+
+                if !missing.is_empty() {
+                    res.insert_header(("X-Missing-Prototypes", missing.join(",")));
+                }
+
+                res.body(image)
+            }
+            RenderFormat::Json => HttpResponse::Ok().json(RenderResult {
+                image: BASE64.encode(image),
+                thumbnail: thumbnail.map(|t| BASE64.encode(t)),
+                missing,
+            }),
+        }
+    }
+
+    type RenderedBP = (Vec<u8>, Vec<String>, Option<Vec<u8>>);
+
+    fn decode_rendered_bp(
+        msg: &[u8],
+    ) -> std::result::Result<RenderedBP, api_capnp::response::ErrorType> {
+        use api_capnp::response::{self, ErrorType};
+
+        let reader = serialize::read_message(msg, ReaderOptions::new())
+            .map_err(|_| ErrorType::ProcessingError)?;
+        let res = reader
+            .get_root::<response::Reader>()
+            .map_err(|_| ErrorType::ProcessingError)?;
+
+        match res.which().map_err(|_| ErrorType::ProcessingError)? {
+            response::RenderedBp(r) => {
+                let image = r
+                    .get_image()
+                    .map_err(|_| ErrorType::ProcessingError)?
+                    .to_vec();
+                let missing = r
+                    .get_missing()
+                    .map_err(|_| ErrorType::ProcessingError)?
+                    .iter()
+                    .filter_map(|m| m.ok()?.to_string().ok())
+                    .collect();
+                let thumbnail = if r.has_thumbnail() {
+                    r.get_thumbnail().ok().map(<[u8]>::to_vec)
+                } else {
+                    None
+                };
+
+                Ok((image, missing, thumbnail))
+            }
+            response::RequestError(err) => Err(err.unwrap_or(ErrorType::ProcessingError)),
+            _ => Err(ErrorType::ProcessingError),
+        }
+    }
+
+    enum QueueError {
+        Full,
+        Failed,
+    }
+
+    /// Puts a request into the shared processing queue and waits for its serialized response.
+    ///
+    /// The progress of the request is reported on `progress_tx` if given, starting with its queue position.
+    async fn enqueue(
+        data: &web::Data<Arc<Mutex<ServerData>>>,
+        req: ApiRequest,
+        progress_tx: Option<ProgressChannel>,
+    ) -> std::result::Result<(Vec<u8>, bool), QueueError> {
+        let id = req.get_id();
+        let (res_tx, res_rx) = oneshot::channel();
+
+        {
+            let app_data = data.lock().await;
+
+            if app_data.input.capacity() == 0 {
+                warn!("queue is full");
+                return Err(QueueError::Full);
+            }
+
+            if let Some(progress_tx) = &progress_tx {
+                let position = app_data.input.max_capacity() - app_data.input.capacity();
+                progress_tx
+                    .send(progress_message(id, &Progress::Queued { position }))
+                    .ok();
+            }
+
+            if let Err(err) = app_data
+                .input
+                .send((req, res_tx, progress_tx))
+                .await
+                .change_context(Error)
+            {
+                error!("{err:?}");
+                return Err(QueueError::Failed);
+            }
+        }
+
+        res_rx.await.map_err(|err| {
+            error!("failed to receive result for {id}: {err:?}");
+            QueueError::Failed
+        })
+    }
+
+    fn error_message(id: u64, error: api_capnp::response::ErrorType) -> Vec<u8> {
+        let mut message = Builder::new_default();
+        let mut response = message.init_root::<api_capnp::response::Builder>();
+        response.set_id(id);
+        response.set_request_error(error);
+
+        serialize::write_message_segments_to_words(&message)
+    }
+
+    fn progress_message(id: u64, progress: &Progress) -> Vec<u8> {
+        let mut message = Builder::new_default();
+        let mut response = message.init_root::<api_capnp::response::Builder>();
+        response.set_id(id);
+
+        let to_u32 = |n: usize| u32::try_from(n).unwrap_or(u32::MAX);
+        let mut res = response.init_progress();
+        match progress {
+            Progress::Queued { position } => res.set_queued(to_u32(*position)),
+            Progress::ResolvingMods => res.set_resolving_mods(()),
+            Progress::DownloadingMod {
+                name,
+                version,
+                downloaded,
+                total,
+            } => {
+                let mut download = res.init_downloading_mod();
+                download.set_name(name.as_str().into());
+                download.set_version(version.to_string().as_str().into());
+                download.set_downloaded(*downloaded);
+                download.set_total(total.unwrap_or_default());
+            }
+            Progress::DumpingPrototypes => res.set_dumping_prototypes(()),
+            Progress::Rendering { done, total } => {
+                let mut rendering = res.init_rendering();
+                rendering.set_done(to_u32(*done));
+                rendering.set_total(to_u32(*total));
+            }
+        }
+
+        serialize::write_message_segments_to_words(&message)
+    }
+
     struct ScannerWs(web::Data<Arc<Mutex<ServerData>>>);
 
     impl actix::Actor for ScannerWs {
@@ -902,11 +2243,34 @@ pub mod server {
     #[rtype(result = "()")]
     struct RequestRunner(Bytes);
 
+    /// Response for the client, all responses go through the mailbox to keep progress & result in order.
+    #[derive(Message)]
+    #[rtype(result = "()")]
+    struct Respond {
+        msg: Option<Vec<u8>>,
+        close: bool,
+    }
+
+    impl Handler<Respond> for ScannerWs {
+        type Result = ();
+
+        fn handle(&mut self, res: Respond, ctx: &mut Self::Context) {
+            if let Some(msg) = res.msg {
+                ctx.binary(msg);
+            }
+
+            if res.close {
+                ctx.close(None);
+            }
+        }
+    }
+
     impl Handler<RequestRunner> for ScannerWs {
         type Result = ResponseActFuture<Self, ()>;
 
         fn handle(&mut self, msg: RequestRunner, ctx: &mut Self::Context) -> Self::Result {
             let data = self.0.clone();
+            let addr = ctx.address();
 
             Box::pin(
                 async move {
@@ -914,51 +2278,42 @@ pub mod server {
 
                     let Some(req) = ApiRequest::deserialize(msg.0.reader()) else {
                         warn!("request deserialization failed");
-                        return (None, true);
+                        addr.do_send(Respond {
+                            msg: None,
+                            close: true,
+                        });
+                        return;
                     };
 
                     let id = req.get_id();
-                    let (res_tx, res_rx) = oneshot::channel();
-
-                    {
-                        let app_data = data.lock().await;
-
-                        if app_data.input.capacity() == 0 {
-                            // queue is full
-                            warn!("queue is full");
+                    let (progress_tx, mut progress_rx) = mpsc::unbounded_channel();
+                    let respond = |msg| {
+                        addr.do_send(Respond {
+                            msg: Some(msg),
+                            close: false,
+                        });
+                    };
 
-                            let mut message = Builder::new_default();
-                            let mut err = message.init_root::<api_capnp::response::Builder>();
-                            err.set_id(id);
-                            err.set_request_error(api_capnp::response::ErrorType::QueueFull);
+                    let res = {
+                        let res = enqueue(&data, req, Some(progress_tx));
+                        pin_utils::pin_mut!(res);
 
-                            return (
-                                Some(serialize::write_message_segments_to_words(&message)),
-                                false,
-                            );
+                        loop {
+                            tokio::select! {
+                                biased;
+                                Some(msg) = progress_rx.recv() => respond(msg),
+                                res = &mut res => break res,
+                            }
                         }
+                    };
 
-                        if let Err(err) = app_data
-                            .input
-                            .send((req, res_tx))
-                            .await
-                            .change_context(Error)
-                        {
-                            error!("{err:?}");
-
-                            let mut message = Builder::new_default();
-                            let mut err = message.init_root::<api_capnp::response::Builder>();
-                            err.set_id(id);
-                            err.set_request_error(api_capnp::response::ErrorType::ProcessingError);
-
-                            return (
-                                Some(serialize::write_message_segments_to_words(&message)),
-                                false,
-                            );
-                        }
+                    // progress that was reported right before the result, later progress of a
+                    // timed out request is dropped
+                    while let Ok(msg) = progress_rx.try_recv() {
+                        respond(msg);
                     }
 
-                    match res_rx.await {
+                    let (msg, close) = match res {
                         Ok((msg, close)) => {
                             if close {
                                 (None, true)
@@ -966,114 +2321,207 @@ pub mod server {
                                 (Some(msg), false)
                             }
                         }
-                        Err(err) => {
-                            error!("{err:?}");
-
-                            let mut message = Builder::new_default();
-                            let mut err = message.init_root::<api_capnp::response::Builder>();
-                            err.set_id(id);
-                            err.set_request_error(api_capnp::response::ErrorType::ProcessingError);
+                        Err(QueueError::Full) => (
+                            Some(error_message(id, api_capnp::response::ErrorType::QueueFull)),
+                            false,
+                        ),
+                        Err(QueueError::Failed) => (
+                            Some(error_message(
+                                id,
+                                api_capnp::response::ErrorType::ProcessingError,
+                            )),
+                            false,
+                        ),
+                    };
 
-                            (
-                                Some(serialize::write_message_segments_to_words(&message)),
-                                false,
-                            )
-                        }
-                    }
+                    addr.do_send(Respond { msg, close });
                 }
-                .into_actor(self)
-                .map(|(send, close), _act, ctx| {
-                    if let Some(send) = send {
-                        ctx.binary(send);
-                    }
-
-                    if close {
-                        ctx.close(None);
-                    }
-                }),
+                .into_actor(self),
             )
         }
     }
-}
 
-fn calculate_target_size(
-    bp: &blueprint::Blueprint,
-    data: &DataUtil,
-    target_res: f64,
-    min_scale: f64,
-) -> Option<TargetSize> {
-    const TILE_RES: f64 = 32.0;
+    #[cfg(test)]
+    mod tests {
+        use super::*;
 
-    let mut min_x = f64::MAX;
-    let mut min_y = f64::MAX;
-    let mut max_x = f64::MIN;
-    let mut max_y = f64::MIN;
+        #[tokio::test]
+        async fn timed_out_job_releases_its_worker() {
+            // stands in for a render that checks for cancellation between entities
+            let mut job = BlockingJob::spawn(|cancelled: &AtomicBool| {
+                while !cancelled.load(Ordering::Relaxed) {
+                    std::thread::sleep(Duration::from_millis(1));
+                }
+            });
 
-    for entity in &bp.entities {
-        let Some(e_proto) = data.get_entity(&entity.name) else {
-            continue;
-        };
+            let outcome = job
+                .wait(Duration::from_millis(10), std::future::pending())
+                .await;
+            assert!(matches!(outcome, JobOutcome::TimedOut));
 
-        let e_pos: MapPosition = (&entity.position).into();
-        let c_box = e_proto.drawing_box();
+            let stopped = tokio::time::timeout(Duration::from_secs(5), job.stop()).await;
+            assert!(stopped.is_ok());
+        }
+
+        fn round_trip(build: impl FnOnce(api_capnp::request::Builder)) -> Option<ApiRequest> {
+            let mut message = Builder::new_default();
+            build(message.init_root::<api_capnp::request::Builder>());
+
+            let data = serialize::write_message_segments_to_words(&message);
+            ApiRequest::deserialize(data.as_slice())
+        }
 
-        let tl = &e_pos + c_box.top_left();
-        let br = &e_pos + c_box.bottom_right();
+        #[test]
+        fn render_bp_request() {
+            let req = round_trip(|mut req| {
+                req.set_id(1);
+                let mut r = req.init_render_bp();
+                r.set_bp_string("0eNq".into());
+                r.set_preset("K2".into());
+                let mut mods = r.init_mods(1);
+                mods.set(0, "foo".into());
+            });
 
-        if tl.x() < min_x {
-            min_x = tl.x();
+            assert_eq!(
+                req,
+                Some(ApiRequest::RenderBP {
+                    id: 1,
+                    bp_string: "0eNq".to_owned(),
+                    preset: "K2".to_owned(),
+                    mods: vec!["foo".to_owned()],
+                })
+            );
         }
 
-        if tl.y() < min_y {
-            min_y = tl.y();
+        #[test]
+        fn render_thumbnail_request() {
+            let req = round_trip(|mut req| {
+                req.set_id(2);
+                let mut r = req.init_render_thumbnail();
+                r.set_bp_string("0eNq".into());
+                r.set_preset("".into());
+                r.init_mods(0);
+                r.set_size(64);
+            });
+
+            assert_eq!(
+                req,
+                Some(ApiRequest::RenderThumbnail {
+                    id: 2,
+                    bp_string: "0eNq".to_owned(),
+                    preset: String::new(),
+                    mods: Vec::new(),
+                    size: 64,
+                })
+            );
         }
 
-        if br.x() > max_x {
-            max_x = br.x();
-        }
+        #[test]
+        fn rendering_progress_response() {
+            use api_capnp::response::{self, progress};
+
+            let msg = progress_message(3, &Progress::Rendering { done: 5, total: 10 });
+            let reader = serialize::read_message(msg.as_slice(), ReaderOptions::new()).unwrap();
+            let res = reader.get_root::<response::Reader>().unwrap();
+            assert_eq!(res.get_id(), 3);
+
+            let Ok(response::Progress(Ok(p))) = res.which() else {
+                panic!("expected a progress response");
+            };
+            let Ok(progress::Rendering(rendering)) = p.which() else {
+                panic!("expected rendering progress");
+            };
+
+            assert_eq!((rendering.get_done(), rendering.get_total()), (5, 10));
+        }
+    }
+}
+
+/// Top left & bottom right corner (whole tiles) of everything that is drawn for a blueprint.
+/// Area covered by a tile and the transitions it draws into its neighbours.
+fn tile_box(position: &blueprint::Position) -> (MapPosition, MapPosition) {
+    let x = f64::from(position.x).floor();
+    let y = f64::from(position.y).floor();
+
+    (
+        MapPosition::XY {
+            x: x - 1.0,
+            y: y - 1.0,
+        },
+        MapPosition::XY {
+            x: x + 2.0,
+            y: y + 2.0,
+        },
+    )
+}
+
+/// Smallest area with whole tile coordinates that contains all boxes with a margin of at least half a tile.
+fn bounds(
+    boxes: impl IntoIterator<Item = (MapPosition, MapPosition)>,
+) -> Option<(MapPosition, MapPosition)> {
+    let mut min_x = f64::MAX;
+    let mut min_y = f64::MAX;
+    let mut max_x = f64::MIN;
+    let mut max_y = f64::MIN;
+
+    for (tl, br) in boxes {
+        min_x = min_x.min(tl.x());
+        min_y = min_y.min(tl.y());
+        max_x = max_x.max(br.x());
+        max_y = max_y.max(br.y());
+    }
+
+    let min_x = (min_x - 0.5).floor();
+    let min_y = (min_y - 0.5).floor();
+    let max_x = (max_x + 0.5).ceil();
+    let max_y = (max_y + 0.5).ceil();
 
-        if br.y() > max_y {
-            max_y = br.y();
-        }
+    if max_x - min_x <= 0.0 || max_y - min_y <= 0.0 {
+        return None;
     }
 
-    for tile in &bp.tiles {
-        let Some(t_proto) = data.get_tile(&tile.name) else {
-            continue;
-        };
+    Some((
+        MapPosition::XY { x: min_x, y: min_y },
+        MapPosition::XY { x: max_x, y: max_y },
+    ))
+}
 
-        let t_pos: MapPosition = (&tile.position).into();
-        let (x, y) = t_pos.as_tuple();
+fn calculate_bounds(
+    bp: &blueprint::Blueprint,
+    data: &DataUtil,
+) -> Option<(MapPosition, MapPosition)> {
+    let entities = bp.entities.iter().filter_map(|entity| {
+        let e_proto = data.get_entity(&entity.name)?;
+        let e_pos: MapPosition = (&entity.position).into();
+        let c_box = e_proto.drawing_box();
 
-        if x < min_x {
-            min_x = x;
-        }
+        Some((&e_pos + c_box.top_left(), &e_pos + c_box.bottom_right()))
+    });
 
-        if y < min_y {
-            min_y = y;
-        }
+    let tiles = bp
+        .tiles
+        .iter()
+        .filter(|tile| data.get_tile(&tile.name).is_some())
+        .map(|tile| tile_box(&tile.position));
 
-        if x > max_x {
-            max_x = x;
-        }
+    bounds(entities.chain(tiles))
+}
 
-        if y > max_y {
-            max_y = y;
-        }
-    }
+fn calculate_target_size(
+    bp: &blueprint::Blueprint,
+    data: &DataUtil,
+    target_res: f64,
+    min_scale: f64,
+) -> Option<TargetSize> {
+    const TILE_RES: f64 = 32.0;
 
-    let min_x = (min_x - 0.5).floor();
-    let min_y = (min_y - 0.5).floor();
-    let max_x = (max_x + 0.5).ceil();
-    let max_y = (max_y + 0.5).ceil();
+    let (top_left, bottom_right) = calculate_bounds(bp, data)?;
+    let (min_x, min_y) = top_left.as_tuple();
+    let (max_x, max_y) = bottom_right.as_tuple();
 
     let width = (max_x - min_x).abs().ceil();
     let height = (max_y - min_y).abs().ceil();
 
-    if width == 0.0 || height == 0.0 {
-        return None;
-    }
-
     // let scale = (f64::from(target_res) / (width * height * TILE_RES))
     //     .sqrt()
     //     .max(min_scale);
@@ -1086,8 +2534,8 @@ fn calculate_target_size(
         (width * tile_res).ceil() as u32,
         (height * tile_res).ceil() as u32,
         scale,
-        MapPosition::XY { x: min_x, y: min_y },
-        MapPosition::XY { x: max_x, y: max_y },
+        top_left,
+        bottom_right,
     ))
 }
 
@@ -1128,17 +2576,176 @@ fn bp_entity2render_opts(
             .as_ref()
             .is_some_and(|c| c.connect_to_logistic_network.unwrap_or_default()),
         fluid_recipe: data.recipe_has_fluid(&value.recipe),
+        modules: sorted_items(&value.items)
+            .into_iter()
+            .filter_map(|(name, count)| {
+                let module = data.get_module(name)?;
+                Some(std::iter::repeat_n(module, count as usize))
+            })
+            .flatten()
+            .collect(),
+    }
+}
+
+/// Item requests of an entity in a stable order.
+fn sorted_items(items: &blueprint::ItemRequest) -> Vec<(&str, u32)> {
+    let mut items = items
+        .iter()
+        .map(|(name, count)| (name.as_str(), *count))
+        .collect::<Vec<_>>();
+    items.sort_unstable();
+    items
+}
+
+/// Offsets of the alt-mode filter icons, up to 4 icons are laid out in a 2x2 grid.
+fn filter_icon_offsets(count: usize) -> Vec<Vector> {
+    let start = match count {
+        0 | 1 => Vector::Tuple(0.0, 0.0),
+        2 => Vector::Tuple(-0.25, 0.0),
+        _ => Vector::Tuple(-0.25, -0.25),
+    };
+
+    (0..count.min(4))
+        .map(|idx| start + Vector::Tuple((idx % 2) as f64 * 0.5, (idx / 2) as f64 * 0.5))
+        .collect()
+}
+
+/// Height of the counts below filter icons in tiles.
+const FILTER_COUNT_SIZE: f64 = 0.25;
+
+/// Shortens a count like the game does for signal & request counts: `1500` becomes `1.5k`, `12345` becomes `12k`.
+fn short_count(count: i64) -> String {
+    let abs = count.unsigned_abs();
+    let (value, suffix) = match abs {
+        0..=999 => return count.to_string(),
+        1_000..=999_999 => (abs as f64 / 1e3, "k"),
+        1_000_000..=999_999_999 => (abs as f64 / 1e6, "M"),
+        _ => (abs as f64 / 1e9, "G"),
+    };
+
+    let sign = if count < 0 { "-" } else { "" };
+    let value = if value < 10.0 {
+        (value * 10.0).floor() / 10.0
+    } else {
+        value.floor()
+    };
+
+    format!("{sign}{value}{suffix}")
+}
+
+/// Icon of an item, fluid or virtual signal.
+fn get_signal_icon(
+    signal: &SignalID,
+    data: &DataUtil,
+    scale: f64,
+    used_mods: &UsedMods,
+    image_cache: &mut ImageCache,
+) -> Option<types::GraphicsOutput> {
+    match signal {
+        SignalID::Item { name } => {
+            data.get_item_icon(name.as_deref()?, scale, used_mods, image_cache)
+        }
+        SignalID::Fluid { name } => {
+            data.get_fluid_icon(name.as_deref()?, scale, used_mods, image_cache)
+        }
+        SignalID::Virtual { name } => {
+            data.get_signal_icon(name.as_deref()?, scale, used_mods, image_cache)
+        }
+    }
+}
+
+/// Height of the train stop name labels in tiles.
+const STATION_NAME_SIZE: f64 = 0.6;
+
+/// Size of the alt-mode module & item request icons in tiles.
+const ITEM_ICON_SIZE: f64 = 0.5;
+
+/// Offsets of the alt-mode module icons relative to the entity position.
+///
+/// Icons that don't fit into the configured rows are left out.
+fn module_icon_offsets(spec: &ModuleSpecification, count: usize) -> Vec<Vector> {
+    let per_row = spec
+        .module_info_max_icons_per_row
+        .map_or_else(|| usize::from(spec.module_slots), usize::from)
+        .max(1);
+    let count = spec
+        .module_info_max_icon_rows
+        .map_or(count, |rows| count.min(usize::from(rows) * per_row));
+    let rows = count.div_ceil(per_row);
+
+    let separation =
+        ITEM_ICON_SIZE * f64::from(spec.module_info_separation_multiplier.unwrap_or(1.1));
+    let shift = spec
+        .module_info_icon_shift
+        .unwrap_or(Vector::Tuple(0.0, 0.7));
+    let first_row = if rows > 1 {
+        shift.y()
+            + f64::from(
+                spec.module_info_multi_row_initial_height_modifier
+                    .unwrap_or(-0.1),
+            )
+    } else {
+        shift.y()
+    };
+
+    (0..count)
+        .map(|idx| {
+            let row = idx / per_row;
+            let in_row = per_row.min(count - row * per_row);
+            let column = (idx % per_row) as f64 - (in_row - 1) as f64 / 2.0;
+
+            Vector::Tuple(
+                column.mul_add(separation, shift.x()),
+                (row as f64).mul_add(separation, first_row),
+            )
+        })
+        .collect()
+}
+
+/// Offsets of the alt-mode item request icons, rows of up to 4 icons centered on the entity.
+fn request_icon_offsets(count: usize) -> Vec<Vector> {
+    const PER_ROW: usize = 4;
+
+    let rows = count.div_ceil(PER_ROW);
+    (0..count)
+        .map(|idx| {
+            let row = idx / PER_ROW;
+            let in_row = PER_ROW.min(count - row * PER_ROW);
+            let column = (idx % PER_ROW) as f64 - (in_row - 1) as f64 / 2.0;
+            let row = row as f64 - (rows - 1) as f64 / 2.0;
+
+            Vector::Tuple(column * ITEM_ICON_SIZE, row * ITEM_ICON_SIZE)
+        })
+        .collect()
+}
+
+/// Rotates a north facing indicator sprite to the given cardinal direction.
+fn rotate_indicator(
+    img: &image::DynamicImage,
+    direction: Direction,
+) -> Option<image::DynamicImage> {
+    match direction {
+        Direction::North => Some(img.clone()),
+        Direction::East => Some(imageops::rotate90(img).into()),
+        Direction::South => Some(imageops::rotate180(img).into()),
+        Direction::West => Some(imageops::rotate270(img).into()),
+        _ => None,
     }
 }
 
+/// Draws the blueprint into `render_layers`, returns the names of unknown prototypes.
+///
+/// Setting `cancelled` stops the render at the next progress report, `None` is returned in that case.
 #[allow(clippy::too_many_lines)]
 fn render_bp(
     bp: &blueprint::Blueprint,
     data: &prototypes::DataUtil,
     used_mods: &UsedMods,
-    mut render_layers: RenderLayerBuffer,
+    render_layers: &mut RenderLayerBuffer,
     image_cache: &mut ImageCache,
-) -> Option<(image::DynamicImage, HashSet<String>)> {
+    progress: &dyn Fn(&Progress),
+    cancelled: &AtomicBool,
+) -> Option<HashSet<String>> {
     let mut unknown = HashSet::new();
     let mut wire_connections = EntityWireConnections::new();
     let mut pipe_connections = HashMap::<MapPosition, HashSet<Direction>>::new();
@@ -1159,6 +2766,43 @@ fn render_bp(
         return None;
     };
 
+    let indicator_line = util_sprites.indication_line.render(
+        render_layers.scale() * 1.5,
+        used_mods,
+        image_cache,
+        &SimpleGraphicsRenderOpts::default(),
+    );
+
+    let mut util_sprite = |name: &str| {
+        util_sprites.sprites.get(name).and_then(|s| {
+            s.render(
+                render_layers.scale() * 1.5,
+                used_mods,
+                image_cache,
+                &SimpleGraphicsRenderOpts::default(),
+            )
+        })
+    };
+    let fluid_arrow = util_sprite("fluid_indication_arrow");
+    let fluid_arrow_both_ways = util_sprite("fluid_indication_arrow_both_ways");
+
+    if let Some(font) = Font::load(used_mods) {
+        render_layers.set_font(font);
+    } else {
+        warn!("failed to load a font, labels will not be drawn");
+    }
+    let item_background = util_sprites
+        .sprites
+        .get("entity_info_dark_background")
+        .and_then(|s| {
+            s.render(
+                render_layers.scale() / ITEM_ICON_SIZE,
+                used_mods,
+                image_cache,
+                &SimpleGraphicsRenderOpts::default(),
+            )
+        });
+
     // pipe / heat connections
     bp.entities.iter().for_each(|e| {
         let Some(e_data) = data.get_entity(&e.name) else {
@@ -1186,11 +2830,19 @@ fn render_bp(
             });
     });
 
-    // render entities
+    // render entities, progress is reported in 1% steps
+    let total = bp.entities.len();
+    let progress_step = (total / 100).max(1);
     let rendered_count = bp
         .entities
         .iter()
-        .filter_map(|e| {
+        .enumerate()
+        .take_while(|(idx, _)| idx % progress_step != 0 || !cancelled.load(Ordering::Relaxed))
+        .filter_map(|(idx, e)| {
+            if idx % progress_step == 0 {
+                progress(&Progress::Rendering { done: idx, total });
+            }
+
             if !data.contains_entity(&e.name) {
                 unknown.insert(e.name.clone());
                 return None;
@@ -1359,6 +3011,169 @@ fn render_bp(
                 }
             }
 
+            // inserter pickup / drop indicators
+            'inserter_arrows: {
+                let Some((pickup, drop)) = data.get_inserter_vectors(&e.name) else {
+                    break 'inserter_arrows;
+                };
+
+                let custom_vector = |pos: &Option<blueprint::Position>, default: Vector| {
+                    pos.as_ref()
+                        .map_or(default, |p| (f64::from(p.x), f64::from(p.y)).into())
+                };
+                let pickup = e
+                    .direction
+                    .rotate_vector(custom_vector(&e.pickup_position, pickup));
+                let drop = e
+                    .direction
+                    .rotate_vector(custom_vector(&e.drop_position, drop));
+
+                // the line lies across the pickup position, the arrow points from pickup to drop
+                if let Some((line, shift)) = &indicator_line {
+                    let line = if e.direction.is_straight(&Direction::North) {
+                        Some(line.clone())
+                    } else {
+                        rotate_indicator(line, Direction::East)
+                    };
+
+                    if let Some(line) = line {
+                        render_layers.add(
+                            (line, pickup + *shift),
+                            &render_opts.position,
+                            InternalRenderLayer::DirectionOverlay,
+                        );
+                    }
+                }
+
+                if let Some(arrow) = rotate_indicator(&indicator_arrow.0, e.direction.flip()) {
+                    render_layers.add(
+                        (arrow, drop + indicator_arrow.1),
+                        &render_opts.position,
+                        InternalRenderLayer::DirectionOverlay,
+                    );
+                }
+            }
+
+            // fluid box input / output arrows, pipes & tanks don't show them
+            if !matches!(
+                data.get_type(&e.name),
+                Some(
+                    EntityType::Pipe
+                        | EntityType::InfinityPipe
+                        | EntityType::PipeToGround
+                        | EntityType::StorageTank
+                )
+            ) {
+                let indicators = data
+                    .get_entity(&e.name)
+                    .map(|e_data| e_data.fluid_indicators(&render_opts))
+                    .unwrap_or_default();
+
+                for (position, dir, flow) in indicators {
+                    // the direction points from the connection towards the entity
+                    let (arrow, arrow_dir) = match flow {
+                        PipeConnectionType::Input => (&fluid_arrow, dir),
+                        PipeConnectionType::Output => (&fluid_arrow, dir.flip()),
+                        PipeConnectionType::InputOutput => (&fluid_arrow_both_ways, dir),
+                    };
+
+                    let Some((img, shift)) = arrow else {
+                        continue;
+                    };
+
+                    let Some(img) = rotate_indicator(img, arrow_dir) else {
+                        continue;
+                    };
+
+                    // draw the arrow on the edge of the entity instead of the connected tile
+                    render_layers.add(
+                        (img, dir.get_offset() * 0.5 + *shift),
+                        &position,
+                        InternalRenderLayer::DirectionOverlay,
+                    );
+                }
+            }
+
+            // train stop name & limit above the stop
+            if !e.station.is_empty()
+                && matches!(data.get_type(&e.name), Some(EntityType::TrainStop))
+            {
+                let [r, g, b, _] = render_opts
+                    .runtime_tint
+                    .unwrap_or_else(types::Color::white)
+                    .to_rgba();
+                let color = types::Color::RGBA(r, g, b, 1.0);
+
+                render_layers.add_text(
+                    (&strip_rich_text(&e.station), Vector::Tuple(0.0, -2.0)),
+                    STATION_NAME_SIZE,
+                    color,
+                    &render_opts.position,
+                );
+
+                if let Some(limit) = e.manual_trains_limit {
+                    render_layers.add_text(
+                        (
+                            &format!("Limit: {limit}"),
+                            Vector::Tuple(0.0, STATION_NAME_SIZE - 2.0),
+                        ),
+                        STATION_NAME_SIZE * 0.75,
+                        color,
+                        &render_opts.position,
+                    );
+                }
+            }
+
+            // module icons along the bottom & other item requests as a bubble in the center
+            if !e.items.is_empty() {
+                let module_spec = data.get_module_specification(&e.name);
+                let (modules, requests): (Vec<_>, Vec<_>) =
+                    sorted_items(&e.items).into_iter().partition(|(name, _)| {
+                        module_spec.is_some() && data.get_module(name).is_some()
+                    });
+
+                let modules = modules
+                    .into_iter()
+                    .flat_map(|(name, count)| std::iter::repeat_n(name, count as usize))
+                    .collect::<Vec<_>>();
+                let module_offsets = module_spec
+                    .map(|spec| module_icon_offsets(spec, modules.len()))
+                    .unwrap_or_default();
+
+                let icons = modules.into_iter().zip(module_offsets).chain(
+                    requests
+                        .iter()
+                        .map(|(name, _)| *name)
+                        .zip(request_icon_offsets(requests.len())),
+                );
+
+                for (name, offset) in icons {
+                    let Some((icon, shift)) = data.get_item_icon(
+                        name,
+                        render_layers.scale() / ITEM_ICON_SIZE,
+                        used_mods,
+                        image_cache,
+                    ) else {
+                        unknown.insert(name.to_owned());
+                        continue;
+                    };
+
+                    if let Some((background, bg_shift)) = &item_background {
+                        render_layers.add(
+                            (background.clone(), offset + *bg_shift),
+                            &render_opts.position,
+                            InternalRenderLayer::DirectionOverlay,
+                        );
+                    }
+
+                    render_layers.add(
+                        (icon, offset + shift),
+                        &render_opts.position,
+                        InternalRenderLayer::IconOverlay,
+                    );
+                }
+            }
+
             // filter icons / priority arrows
             'filters_priority: {
                 if let Some(prio_in) = &e.input_priority {
@@ -1423,42 +3238,81 @@ fn render_bp(
                         );
                     }
                 }
+            }
 
-                if !e.filters.is_empty() {
-                    let filter_count = e.filters.len();
-                    let mut offset = if filter_count == 1 {
-                        Vector::Tuple(0.0, 0.0)
-                    } else if filter_count == 2 {
-                        Vector::Tuple(-0.25, 0.0)
-                    } else {
-                        Vector::Tuple(-0.25, -0.25)
-                    };
+            // inserter / container filters, logistic requests & constant combinator signals
+            {
+                let items = |filters: Vec<(u16, &str, Option<i64>)>| {
+                    let mut filters = filters;
+                    filters.sort_by_key(|(index, _, _)| *index);
+                    filters
+                        .into_iter()
+                        .map(|(_, name, count)| {
+                            let signal = SignalID::Item {
+                                name: Some(name.to_owned()),
+                            };
+                            (signal, count)
+                        })
+                        .collect::<Vec<_>>()
+                };
+
+                let mut signals = items(
+                    e.filters
+                        .iter()
+                        .chain(e.inventory.iter().flat_map(|i| &i.filters))
+                        .map(|f| (f.index, f.as_str(), None))
+                        .collect(),
+                );
+                signals.extend(items(
+                    e.request_filters
+                        .iter()
+                        .map(|f| (f.index, f.name.as_str(), Some(i64::from(f.count))))
+                        .collect(),
+                ));
+
+                if let Some(behavior) = &e.control_behavior {
+                    let mut filters = behavior.filters.iter().collect::<Vec<_>>();
+                    filters.sort_by_key(|f| f.index);
+                    signals.extend(
+                        filters
+                            .into_iter()
+                            .map(|f| (f.signal.clone(), Some(i64::from(f.count)))),
+                    );
+                }
 
-                    for idx in 0..filter_count.min(4) {
-                        if idx == 2 {
-                            offset += Vector::Tuple(-1.0, 0.5);
-                        }
+                for ((signal, count), offset) in
+                    signals.iter().zip(filter_icon_offsets(signals.len()))
+                {
+                    let Some((icon, shift)) = get_signal_icon(
+                        signal,
+                        data,
+                        render_layers.scale() * 2.2,
+                        used_mods,
+                        image_cache,
+                    ) else {
+                        warn!(
+                            "failed to render filter icon for {signal:?} at {:?} [{}]",
+                            e.position, e.name
+                        );
+                        continue;
+                    };
 
-                        let Some(filter) = data.get_item_icon(
-                            &e.filters[idx],
-                            render_layers.scale() * 2.2,
-                            used_mods,
-                            image_cache,
-                        ) else {
-                            warn!(
-                                "failed to render filter icon for {} at {:?} [{}]",
-                                e.filters[idx], e.position, e.name
-                            );
-                            continue;
-                        };
+                    render_layers.add(
+                        (icon, shift + offset),
+                        &render_opts.position,
+                        InternalRenderLayer::IconOverlay,
+                    );
 
-                        render_layers.add(
-                            (filter.0, filter.1 + offset),
+                    if let Some(count) = count {
+                        render_layers.add_text(
+                            (
+                                &short_count(*count),
+                                offset + Vector::Tuple(0.1, FILTER_COUNT_SIZE * 0.6),
+                            ),
+                            FILTER_COUNT_SIZE,
+                            types::Color::white(),
                             &render_opts.position,
-                            InternalRenderLayer::IconOverlay,
                         );
-
-                        offset += Vector::Tuple(0.5, 0.0);
                     }
                 }
             }
@@ -1500,62 +3354,63 @@ fn render_bp(
                 );
             }
 
-            data.render_entity(
-                &e.name,
-                &render_opts,
-                used_mods,
-                &mut render_layers,
-                image_cache,
-            )
+            data.render_entity(&e.name, &render_opts, used_mods, render_layers, image_cache)
         })
         .count();
 
+    if cancelled.load(Ordering::Relaxed) {
+        debug!("render cancelled");
+        return None;
+    }
+
+    progress(&Progress::Rendering { done: total, total });
     info!("entities: {}, layers: {rendered_count}", bp.entities.len());
 
     // render tiles
-    let rendered_count = bp
+    let tiles = bp
         .tiles
         .iter()
-        .filter_map(|t| {
-            let position: MapPosition = (&t.position).into();
-            data.render_tile(
-                &t.name,
-                &(position + MapPosition::Tuple(0.5, 0.5)),
-                used_mods,
-                &mut render_layers,
-                image_cache,
+        .map(|t| {
+            (
+                (t.position.x.floor() as i32, t.position.y.floor() as i32),
+                t.name.as_str(),
             )
         })
-        .count();
+        .collect::<HashMap<_, _>>();
+    let rendered_count = data.render_tiles(&tiles, used_mods, render_layers, image_cache);
 
     info!("tiles: {}, layers: {rendered_count}", bp.tiles.len());
 
     render_layers.draw_wires(&wire_connections, util_sprites, used_mods, image_cache);
     render_layers.generate_background();
 
-    Some((render_layers.combine(), unknown))
+    Some(unknown)
 }
 
+/// Default size (1 side of a square) of thumbnails in pixels.
+const THUMBNAIL_SIZE: u32 = 256;
+
 fn render_thumbnail(
     bp: &blueprint::Data,
     data: &prototypes::DataUtil,
     used_mods: &UsedMods,
     image_cache: &mut ImageCache,
+    size: u32,
 ) -> Option<image::DynamicImage> {
-    static BASE_SCALE: f64 = 0.125;
-    let size = (32.0 / BASE_SCALE).round() as u32;
+    // the item icon covers the whole thumbnail
+    let base_scale = 32.0 / f64::from(size.max(1));
 
     let mut layers = RenderLayerBuffer::new(TargetSize::new(
         size,
         size,
-        BASE_SCALE,
+        base_scale,
         MapPosition::Tuple(-1.0, -1.0),
         MapPosition::Tuple(1.0, 1.0),
     ));
 
     layers.add(
         (
-            data.get_item_icon(bp.item(), BASE_SCALE, used_mods, image_cache)?
+            data.get_item_icon(bp.item(), base_scale, used_mods, image_cache)?
                 .0,
             Vector::Tuple(-0.5, -0.5),
         ),
@@ -1570,11 +3425,11 @@ fn render_thumbnail(
 
     let icon_count = icons.len();
     let (scale, s_x, s_y) = if icon_count == 1 {
-        (BASE_SCALE * 1.2, -0.5, -0.5)
+        (base_scale * 1.2, -0.5, -0.5)
     } else if icon_count == 2 {
-        (BASE_SCALE * 2.2, -0.75, -0.5)
+        (base_scale * 2.2, -0.75, -0.5)
     } else {
-        (BASE_SCALE * 2.2, -0.75, -0.75)
+        (base_scale * 2.2, -0.75, -0.75)
     };
 
     let mut offset = Vector::Tuple(s_x, s_y);
@@ -1588,26 +3443,7 @@ fn render_thumbnail(
                 offset += Vector::Tuple(-1.0, 0.5);
             }
 
-            let res = match &icons[idx].signal {
-                SignalID::Item { name } => data.get_item_icon(
-                    name.clone().unwrap_or_default().as_str(),
-                    scale,
-                    used_mods,
-                    image_cache,
-                ),
-                SignalID::Fluid { name } => data.get_fluid_icon(
-                    name.clone().unwrap_or_default().as_str(),
-                    scale,
-                    used_mods,
-                    image_cache,
-                ),
-                SignalID::Virtual { name } => data.get_signal_icon(
-                    name.clone().unwrap_or_default().as_str(),
-                    scale,
-                    used_mods,
-                    image_cache,
-                ),
-            };
+            let res = get_signal_icon(&icon.signal, data, scale, used_mods, image_cache);
 
             let Some((res, _)) = res else {
                 return;
@@ -1666,6 +3502,7 @@ impl std::fmt::Display for DependencyResolutionError {
 fn resolve_mod_dependencies(
     required: &DependencyList,
     mod_list: &mut ModList,
+    portal_cache: &PortalCache,
 ) -> Result<UsedVersions, DependencyResolutionError> {
     match mod_list
         .solve_dependencies(required)
@@ -1676,7 +3513,11 @@ fn resolve_mod_dependencies(
         Err(err) => info!("{err:?}"),
     }
 
-    info!("fetching dependency info from mod portal");
+    if portal_cache.is_offline() {
+        info!("using cached mod portal info from {:?}", portal_cache.dir());
+    } else {
+        info!("fetching dependency info from mod portal");
+    }
 
     let mut process_queue = required.keys().cloned().collect::<Vec<_>>();
     let mut fetched_deps = Vec::new();
@@ -1687,16 +3528,42 @@ fn resolve_mod_dependencies(
             continue;
         }
 
-        let info = factorio_api::blocking::full_info(&name)
-            .change_context(DependencyResolutionError)
-            .attach_printable_lazy(|| format!("fetching mod info for {name} failed"))?;
+        let info = match factorio_api::blocking::cached_full_info(portal_cache, &name) {
+            Ok(info) => info,
+            Err(factorio_api::FactorioApiError::NotCached(_)) => {
+                // the local info.json (if installed) is all we know about it
+                debug!("no cached portal info for {name}");
+                fetched_deps.push(name);
+                continue;
+            }
+            Err(err) => {
+                return Err(report!(err))
+                    .change_context(DependencyResolutionError)
+                    .attach_printable_lazy(|| format!("fetching mod info for {name} failed"))
+            }
+        };
 
-        let deps_info = info
+        let mut deps_info = info
             .releases
             .into_iter()
             .map(|r| (r.version, r.info_json.dependencies))
             .collect::<HashMap<_, _>>();
 
+        // only installed releases can be used without downloading them,
+        // their info.json stays the source of truth
+        if portal_cache.is_offline() {
+            let installed = mod_list.as_list().get(&name);
+            deps_info.retain(|version, _| {
+                installed.is_some_and(|entry| matches!(entry.versions.get(version), Some(Some(_))))
+            });
+
+            if let Some(entry) = installed {
+                for (version, deps) in &entry.known_dependencies {
+                    deps_info.insert(*version, deps.clone());
+                }
+            }
+        }
+
         mod_list.set_dependency_info(&name.clone(), deps_info.clone());
 
         let queue_add = deps_info
@@ -1724,12 +3591,18 @@ fn resolve_mod_dependencies(
         .change_context(DependencyResolutionError)
 }
 
+/// How often a mod download is tried before giving up.
+const DOWNLOAD_ATTEMPTS: u32 = 3;
+
+/// Number of mods that are downloaded at the same time.
+const DOWNLOAD_WORKERS: usize = 4;
+
 #[derive(Debug)]
 enum ModDownloadError {
     MissingCredentials,
     TriedToDownloadWubeMod(String, Version),
+    DownloadsNotStarted,
     DownloadFailed(String, Version),
-    SaveFailed(String, Version),
 }
 
 impl Context for ModDownloadError {}
@@ -1746,12 +3619,16 @@ impl std::fmt::Display for ModDownloadError {
             Self::DownloadFailed(name, version) => {
                 write!(f, "failed to download mod {name} v{version}")
             }
-            Self::SaveFailed(name, version) => write!(f, "failed to save mod {name} v{version}",),
+            Self::DownloadsNotStarted => write!(f, "failed to start the mod downloads"),
         }
     }
 }
 
-fn download_mods(missing: UsedVersions, factorio_dir: &Path) -> Result<(), ModDownloadError> {
+fn download_mods(
+    missing: UsedVersions,
+    factorio_dir: &Path,
+    progress: &dyn Fn(&Progress),
+) -> Result<(), ModDownloadError> {
     let mods_path = factorio_dir.join("mods");
 
     let (username, token) = {
@@ -1778,19 +3655,105 @@ fn download_mods(missing: UsedVersions, factorio_dir: &Path) -> Result<(), ModDo
         }
     };
 
+    let mut downloads = Vec::with_capacity(missing.len());
     for (name, version) in missing {
         ensure!(
             !Mod::wube_mods().contains(&name.as_str()),
             ModDownloadError::TriedToDownloadWubeMod(name, version)
         );
 
-        info!("downloading {name} v{version}");
-        let dl = factorio_api::blocking::fetch_mod(&name, &version, &username, &token)
-            .change_context(ModDownloadError::DownloadFailed(name.clone(), version))?;
+        downloads.push(ModDownload {
+            path: mods_path.join(format!("{name}_{version}.zip")),
+            name,
+            version,
+        });
+    }
+
+    info!(
+        "downloading {} mods with up to {DOWNLOAD_WORKERS} at once",
+        downloads.len()
+    );
+
+    let logged = RefCell::new(HashMap::new());
+    let results = factorio_api::blocking::download_mods(
+        &downloads,
+        (&username, &token),
+        DOWNLOAD_WORKERS,
+        DOWNLOAD_ATTEMPTS,
+        |download, dl_progress| {
+            log_download(&mut logged.borrow_mut(), download, dl_progress);
+            progress(&Progress::DownloadingMod {
+                name: download.name.clone(),
+                version: download.version,
+                downloaded: dl_progress.downloaded,
+                total: dl_progress.total,
+            });
+        },
+    )
+    .change_context(ModDownloadError::DownloadsNotStarted)?;
+
+    let mut failed: Option<Report<ModDownloadError>> = None;
+    for (download, res) in downloads.into_iter().zip(results) {
+        let Err(err) = res else {
+            continue;
+        };
 
-        fs::write(mods_path.join(format!("{name}_{version}.zip")), dl)
-            .change_context(ModDownloadError::SaveFailed(name, version))?;
+        let err = report!(err).change_context(ModDownloadError::DownloadFailed(
+            download.name,
+            download.version,
+        ));
+
+        match &mut failed {
+            Some(failed) => failed.extend_one(err),
+            None => failed = Some(err),
+        }
     }
 
-    Ok(())
+    failed.map_or(Ok(()), Err)
+}
+
+/// Logs a progress bar of a download every 10%.
+fn log_download(
+    logged: &mut HashMap<String, u64>,
+    download: &ModDownload,
+    progress: factorio_api::DownloadProgress,
+) {
+    const WIDTH: u64 = 10;
+
+    let Some(total) = progress.total.filter(|total| *total > 0) else {
+        return;
+    };
+
+    let step = (progress.downloaded * WIDTH / total).min(WIDTH);
+    if logged.insert(download.name.clone(), step) == Some(step) {
+        return;
+    }
+
+    info!(
+        "[{}{}] {:>3}% {} v{} ({:.1} / {:.1} MiB)",
+        "#".repeat(step as usize),
+        " ".repeat((WIDTH - step) as usize),
+        step * 100 / WIDTH,
+        download.name,
+        download.version,
+        progress.downloaded as f64 / 1024.0 / 1024.0,
+        total as f64 / 1024.0 / 1024.0
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    #[test]
+    fn single_tile_transitions_are_on_the_canvas() {
+        let position = blueprint::Position { x: 3.0, y: -2.0 };
+        let (tl, br) = bounds([tile_box(&position)]).unwrap();
+
+        // the tile covers (3, -2) to (4, -1), its transitions reach one tile further on every side
+        assert!(tl.x() <= 2.0 && tl.y() <= -3.0);
+        assert!(br.x() >= 5.0 && br.y() >= 0.0);
+    }
 }
@@ -0,0 +1,414 @@
+use std::collections::HashSet;
+
+use blueprint::{DeconPlannerData, FilterMode, TileSelectionMode, UpgradePlannerData};
+use image::{imageops, DynamicImage};
+use mod_util::UsedMods;
+use prototypes::{text::Font, DataUtil, InternalRenderLayer, RenderLayerBuffer, TargetSize};
+use types::{Color, ImageCache, MapPosition, RenderableGraphics, SimpleGraphicsRenderOpts, Vector};
+
+/// 64 px per tile, cards are small so they don't need to follow the target resolution.
+const CARD_SCALE: f64 = 0.5;
+
+/// Margin around the card content in tiles.
+const MARGIN: f64 = 0.5;
+
+/// Vertical distance between two rows of icons in tiles.
+const ROW_HEIGHT: f64 = 1.25;
+
+/// Mappings of an upgrade planner are wrapped into a new column after this many rows.
+const MAPPINGS_PER_COLUMN: usize = 10;
+
+/// Width of one upgrade planner column: from icon, arrow, to icon and a 1 tile gap.
+const MAPPING_COLUMN_WIDTH: f64 = 4.0;
+
+/// Filters of a deconstruction planner are wrapped into a new row after this many icons.
+const FILTERS_PER_ROW: usize = 10;
+
+/// Height of the text labels in tiles.
+const LABEL_SIZE: f64 = 0.4;
+
+/// Number of filter icons a label replaces, the label is shortened to fit into them.
+const LABEL_SLOTS: usize = 4;
+
+#[derive(Debug, Clone, Copy)]
+enum CardIcon<'a> {
+    Item(&'a str),
+    Tile(&'a str),
+    Utility(&'static str),
+    Arrow,
+
+    /// Text that starts at the position of the icon, used where no fitting icon exists.
+    Label(&'static str),
+}
+
+struct Card<'a> {
+    icons: Vec<(CardIcon<'a>, MapPosition)>,
+    width: f64,
+    height: f64,
+}
+
+impl<'a> Card<'a> {
+    fn upgrade(planner: &'a UpgradePlannerData) -> Self {
+        let mut mappers = planner.mappers.iter().collect::<Vec<_>>();
+        mappers.sort_by_key(|m| m.index);
+
+        let mappers = mappers
+            .into_iter()
+            .filter(|m| m.from.is_some() || m.to.is_some())
+            .collect::<Vec<_>>();
+
+        let mut icons = Vec::with_capacity(mappers.len() * 3);
+        for (idx, mapper) in mappers.iter().enumerate() {
+            let column = (idx / MAPPINGS_PER_COLUMN) as f64;
+            let row = (idx % MAPPINGS_PER_COLUMN) as f64;
+
+            let x = column.mul_add(MAPPING_COLUMN_WIDTH, MARGIN + 0.5);
+            let y = row.mul_add(ROW_HEIGHT, MARGIN + 0.5);
+
+            if let Some(from) = &mapper.from {
+                icons.push((CardIcon::Item(from.name()), MapPosition::Tuple(x, y)));
+            }
+
+            icons.push((CardIcon::Arrow, MapPosition::Tuple(x + 1.0, y)));
+
+            if let Some(to) = &mapper.to {
+                icons.push((CardIcon::Item(to.name()), MapPosition::Tuple(x + 2.0, y)));
+            }
+        }
+
+        // an empty planner still gets a card with the size of a single mapping
+        let columns = mappers.len().div_ceil(MAPPINGS_PER_COLUMN).max(1) as f64;
+        let rows = mappers.len().clamp(1, MAPPINGS_PER_COLUMN) as f64;
+
+        Self {
+            icons,
+            width: columns.mul_add(MAPPING_COLUMN_WIDTH, 2.0f64.mul_add(MARGIN, -1.0)),
+            height: rows.mul_add(ROW_HEIGHT, 2.0f64.mul_add(MARGIN, -0.25)),
+        }
+    }
+
+    fn deconstruction(planner: &'a DeconPlannerData) -> Self {
+        let mut icons = Vec::new();
+        let mut y = MARGIN + 0.5;
+        let mut max_filters = 0;
+
+        // entities: filter mode marker followed by the filters
+        {
+            // the game ignores the entity filters when only trees & rocks are selected
+            let filters = if planner.trees_and_rocks_only {
+                Vec::new()
+            } else {
+                let mut filters = planner.entity_filters.iter().collect::<Vec<_>>();
+                filters.sort_by_key(|f| f.index);
+
+                filters
+                    .into_iter()
+                    .map(|f| CardIcon::Item(f.as_str()))
+                    .collect()
+            };
+
+            let blacklist = planner.entity_filter_mode == FilterMode::Blacklist
+                && !planner.trees_and_rocks_only;
+
+            if blacklist {
+                icons.push((
+                    CardIcon::Utility("filter_blacklist"),
+                    MapPosition::Tuple(MARGIN + 0.5, y),
+                ));
+            }
+
+            if planner.trees_and_rocks_only {
+                icons.push((
+                    CardIcon::Label("Trees and rocks only"),
+                    MapPosition::Tuple(MARGIN + 2.0 - ROW_HEIGHT / 2.0, y),
+                ));
+                max_filters = LABEL_SLOTS;
+                y += ROW_HEIGHT;
+            } else if blacklist || !filters.is_empty() {
+                max_filters = max_filters.max(filters.len());
+                y = Self::push_filter_rows(&mut icons, filters, y);
+            }
+        }
+
+        // tiles: selection mode marker, the filters only matter if tiles are selected at all
+        {
+            let marker = match planner.tile_selection_mode {
+                TileSelectionMode::Normal => None,
+                TileSelectionMode::Always => Some("check_mark_green"),
+                TileSelectionMode::Never => Some("not_available"),
+                TileSelectionMode::Only => Some("tile_ghost_cursor"),
+            };
+
+            let mut filters = Vec::new();
+            if planner.tile_selection_mode != TileSelectionMode::Never {
+                let mut tile_filters = planner.tile_filters.iter().collect::<Vec<_>>();
+                tile_filters.sort_by_key(|f| f.index);
+                filters.extend(tile_filters.into_iter().map(|f| CardIcon::Tile(f.as_str())));
+            }
+
+            if marker.is_some() || !filters.is_empty() {
+                // separate the tile section from the entity section
+                if !icons.is_empty() {
+                    y += 0.5;
+                }
+
+                if let Some(marker) = marker {
+                    icons.push((
+                        CardIcon::Utility(marker),
+                        MapPosition::Tuple(MARGIN + 0.5, y),
+                    ));
+                }
+
+                if planner.tile_filter_mode == FilterMode::Blacklist && !filters.is_empty() {
+                    icons.push((
+                        CardIcon::Utility("filter_blacklist"),
+                        MapPosition::Tuple(MARGIN + 0.5, ROW_HEIGHT.mul_add(0.5, y)),
+                    ));
+                }
+
+                max_filters = max_filters.max(filters.len());
+                y = Self::push_filter_rows(&mut icons, filters, y);
+            }
+        }
+
+        // an empty planner still gets a card with the size of a single row
+        let columns = max_filters.clamp(1, FILTERS_PER_ROW) as f64;
+        let y = y.max(MARGIN + 0.5 + ROW_HEIGHT);
+
+        Self {
+            icons,
+            width: columns.mul_add(ROW_HEIGHT, 2.0f64.mul_add(MARGIN, 1.25)),
+            height: y - ROW_HEIGHT + 0.5 + MARGIN,
+        }
+    }
+
+    /// Places the filters next to the marker column, returns the y position of the next row.
+    fn push_filter_rows(
+        icons: &mut Vec<(CardIcon<'a>, MapPosition)>,
+        filters: Vec<CardIcon<'a>>,
+        y: f64,
+    ) -> f64 {
+        let count = filters.len();
+        for (idx, filter) in filters.into_iter().enumerate() {
+            let column = (idx % FILTERS_PER_ROW) as f64;
+            let row = (idx / FILTERS_PER_ROW) as f64;
+
+            icons.push((
+                filter,
+                MapPosition::Tuple(
+                    column.mul_add(ROW_HEIGHT, MARGIN + 2.0),
+                    row.mul_add(ROW_HEIGHT, y),
+                ),
+            ));
+        }
+
+        (count.div_ceil(FILTERS_PER_ROW).max(1) as f64).mul_add(ROW_HEIGHT, y)
+    }
+}
+
+/// Renders an upgrade or deconstruction planner as a card of its filter / mapping icons.
+///
+/// Planners without any filters / mappings are rendered as an empty card.
+/// Returns `None` if the data is no planner.
+pub fn render(
+    planner: &blueprint::Data,
+    data: &DataUtil,
+    used_mods: &UsedMods,
+    image_cache: &mut ImageCache,
+) -> Option<(DynamicImage, HashSet<String>)> {
+    let card = match planner {
+        blueprint::Data::UpgradePlanner(planner) => Card::upgrade(planner),
+        blueprint::Data::DeconstructionPlanner(planner) => Card::deconstruction(planner),
+        _ => return None,
+    };
+
+    let tile_res = 32.0 / CARD_SCALE;
+    let mut layers = RenderLayerBuffer::new(TargetSize::new(
+        (card.width * tile_res).round() as u32,
+        (card.height * tile_res).round() as u32,
+        CARD_SCALE,
+        MapPosition::Tuple(0.0, 0.0),
+        MapPosition::Tuple(card.width, card.height),
+    ));
+
+    if card
+        .icons
+        .iter()
+        .any(|(icon, _)| matches!(icon, CardIcon::Label(_)))
+    {
+        if let Some(font) = Font::load(used_mods) {
+            layers.set_font(font);
+        }
+    }
+
+    let util_sprites = data.util_sprites();
+    let icon_scale = CARD_SCALE * 1.25;
+    let mut unknown = HashSet::new();
+
+    for (icon, position) in &card.icons {
+        let (res, layer) = match icon {
+            CardIcon::Item(name) => {
+                let res = data.get_item_icon(name, icon_scale, used_mods, image_cache);
+                if res.is_none() {
+                    unknown.insert((*name).to_owned());
+                }
+
+                (res, InternalRenderLayer::IconOverlay)
+            }
+            CardIcon::Tile(name) => {
+                let res = data.get_tile_icon(name, icon_scale, used_mods, image_cache);
+                if res.is_none() {
+                    unknown.insert((*name).to_owned());
+                }
+
+                (res, InternalRenderLayer::IconOverlay)
+            }
+            CardIcon::Utility(name) => (
+                util_sprites
+                    .and_then(|u| u.sprites.get(*name))
+                    .and_then(|s| {
+                        s.render(
+                            CARD_SCALE,
+                            used_mods,
+                            image_cache,
+                            &SimpleGraphicsRenderOpts::default(),
+                        )
+                    }),
+                InternalRenderLayer::DirectionOverlay,
+            ),
+            CardIcon::Arrow => (
+                util_sprites
+                    .and_then(|u| {
+                        u.indication_arrow.render(
+                            CARD_SCALE,
+                            used_mods,
+                            image_cache,
+                            &SimpleGraphicsRenderOpts::default(),
+                        )
+                    })
+                    .map(|(img, _)| (imageops::rotate90(&img).into(), Vector::Tuple(0.0, 0.0))),
+                InternalRenderLayer::DirectionOverlay,
+            ),
+            CardIcon::Label(text) => {
+                let Some(font) = layers.font() else {
+                    continue;
+                };
+
+                let size = LABEL_SIZE * tile_res;
+                let max_width = (LABEL_SLOTS as f64 * ROW_HEIGHT * tile_res) as u32;
+                let text = font.fit(text, size, max_width);
+                let width = f64::from(font.width(&text, size)) / tile_res;
+
+                // text is drawn centered on its position
+                layers.add_text(
+                    (&text, Vector::Tuple(width / 2.0, 0.0)),
+                    LABEL_SIZE,
+                    Color::white(),
+                    position,
+                );
+                continue;
+            }
+        };
+
+        if let Some(res) = res {
+            layers.add(res, position, layer);
+        }
+    }
+
+    layers.generate_background();
+
+    Some((layers.combine(), unknown))
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    fn upgrade_planner(mappings: usize) -> UpgradePlannerData {
+        let mappers = (0..mappings)
+            .map(|index| {
+                serde_json::json!({
+                    "index": index,
+                    "from": { "type": "entity", "name": "transport-belt" },
+                    "to": { "type": "entity", "name": "fast-transport-belt" },
+                })
+            })
+            .collect::<Vec<_>>();
+
+        serde_json::from_value(serde_json::json!({ "mappers": mappers })).unwrap()
+    }
+
+    fn decon_planner(filters: usize, trees_and_rocks_only: bool) -> DeconPlannerData {
+        let entity_filters = (0..filters)
+            .map(|index| serde_json::json!({ "index": index, "name": "stone-furnace" }))
+            .collect::<Vec<_>>();
+
+        serde_json::from_value(serde_json::json!({
+            "entity_filters": entity_filters,
+            "trees_and_rocks_only": trees_and_rocks_only,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn empty_planners_get_a_card() {
+        let upgrade = UpgradePlannerData::default();
+        let card = Card::upgrade(&upgrade);
+        assert!(card.icons.is_empty());
+        assert!(card.width > 0.0 && card.height > 0.0);
+
+        let decon = DeconPlannerData::default();
+        let card = Card::deconstruction(&decon);
+        assert!(card.icons.is_empty());
+        assert!(card.width > 0.0 && card.height > 0.0);
+    }
+
+    #[test]
+    fn upgrade_mappings_wrap_into_columns() {
+        let planner = upgrade_planner(MAPPINGS_PER_COLUMN + 2);
+        let card = Card::upgrade(&planner);
+
+        // from, arrow & to per mapping
+        assert_eq!(card.icons.len(), (MAPPINGS_PER_COLUMN + 2) * 3);
+
+        let single = upgrade_planner(1);
+        let single = Card::upgrade(&single);
+        assert!((card.width - (single.width + MAPPING_COLUMN_WIDTH)).abs() < f64::EPSILON);
+
+        // the first mapping of the second column is next to the first mapping
+        let first = &card.icons[0].1;
+        let wrapped = &card.icons[MAPPINGS_PER_COLUMN * 3].1;
+        assert!((wrapped.x() - first.x() - MAPPING_COLUMN_WIDTH).abs() < f64::EPSILON);
+        assert!((wrapped.y() - first.y()).abs() < f64::EPSILON);
+
+        // every icon is on the card
+        for (_, pos) in &card.icons {
+            assert!(pos.x() > 0.0 && pos.x() < card.width);
+            assert!(pos.y() > 0.0 && pos.y() < card.height);
+        }
+    }
+
+    #[test]
+    fn decon_filters_wrap_into_rows() {
+        let one_row = decon_planner(FILTERS_PER_ROW, false);
+        let one_row = Card::deconstruction(&one_row);
+        let two_rows = decon_planner(FILTERS_PER_ROW + 1, false);
+        let two_rows = Card::deconstruction(&two_rows);
+
+        assert_eq!(two_rows.icons.len(), FILTERS_PER_ROW + 1);
+        assert!((two_rows.width - one_row.width).abs() < f64::EPSILON);
+        assert!((two_rows.height - one_row.height - ROW_HEIGHT).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn trees_and_rocks_are_labeled() {
+        let planner = decon_planner(3, true);
+        let card = Card::deconstruction(&planner);
+
+        // the entity filters are ignored by the game in this mode
+        assert_eq!(card.icons.len(), 1);
+        assert!(matches!(card.icons[0].0, CardIcon::Label(_)));
+    }
+}
@@ -0,0 +1,168 @@
+use blueprint::{CompareType, Condition, Schedule, SignalID, WaitCondition, WaitConditionType};
+use image::{imageops, DynamicImage, Rgba, RgbaImage};
+use prototypes::text::Font;
+
+use crate::strip_rich_text;
+
+const PADDING: u32 = 16;
+const BACKGROUND: Rgba<u8> = Rgba([0x1b, 0x1b, 0x1b, 0xff]);
+const TRAIN_COLOR: types::Color = types::Color::RGBA(1.0, 0.9, 0.75, 1.0);
+const STATION_COLOR: types::Color = types::Color::RGBA(1.0, 1.0, 1.0, 1.0);
+const CONDITION_COLOR: types::Color = types::Color::RGBA(0.7, 0.7, 0.7, 1.0);
+
+/// The sidebar is never wider than this fraction of the render.
+const MAX_WIDTH_FACTOR: f64 = 0.5;
+
+struct Line {
+    text: String,
+    color: types::Color,
+    indent: u32,
+}
+
+fn signal_name(signal: &SignalID) -> &str {
+    match signal {
+        SignalID::Item { name } | SignalID::Fluid { name } | SignalID::Virtual { name } => {
+            name.as_deref().unwrap_or("?")
+        }
+    }
+}
+
+fn condition_text(condition: Option<&Condition>) -> String {
+    match condition {
+        None => "?".to_owned(),
+        Some(Condition::Signals {
+            first_signal,
+            second_signal,
+            comparator,
+        }) => format!(
+            "{} {comparator} {}",
+            first_signal.as_ref().map_or("?", signal_name),
+            second_signal.as_ref().map_or("?", signal_name)
+        ),
+        Some(Condition::Constant {
+            first_signal,
+            constant,
+            comparator,
+        }) => format!("{} {comparator} {constant}", signal_name(first_signal)),
+    }
+}
+
+fn seconds(ticks: u32) -> String {
+    format!("{}s", f64::from(ticks) / 60.0)
+}
+
+fn wait_condition_text(condition: &WaitCondition) -> String {
+    match &condition.condition {
+        WaitConditionType::Full => "Full cargo inventory".to_owned(),
+        WaitConditionType::Empty => "Empty cargo inventory".to_owned(),
+        WaitConditionType::RobotsInactive => "Robots inactive".to_owned(),
+        WaitConditionType::PassengerPresent => "Passenger present".to_owned(),
+        WaitConditionType::PassengerNotPresent => "Passenger not present".to_owned(),
+        WaitConditionType::Time { ticks } => format!("{} passed", seconds(*ticks)),
+        WaitConditionType::Inactivity { ticks } => format!("{} of inactivity", seconds(*ticks)),
+        WaitConditionType::Circuit { condition } => {
+            format!("Circuit: {}", condition_text(condition.as_ref()))
+        }
+        WaitConditionType::ItemCount { condition } => {
+            format!("Items: {}", condition_text(condition.as_ref()))
+        }
+        WaitConditionType::FluidCount { condition } => {
+            format!("Fluids: {}", condition_text(condition.as_ref()))
+        }
+    }
+}
+
+fn lines(schedules: &[Schedule], size: u32) -> Vec<Line> {
+    let mut lines = Vec::new();
+
+    for (idx, schedule) in schedules.iter().enumerate() {
+        let locomotives = match schedule.locomotives.len() {
+            1 => "1 locomotive".to_owned(),
+            n => format!("{n} locomotives"),
+        };
+        lines.push(Line {
+            text: format!("Train {} ({locomotives})", idx + 1),
+            color: TRAIN_COLOR,
+            indent: 0,
+        });
+
+        for (stop, record) in schedule.schedule.iter().enumerate() {
+            lines.push(Line {
+                text: format!("{}. {}", stop + 1, strip_rich_text(&record.station)),
+                color: STATION_COLOR,
+                indent: size,
+            });
+
+            for (cond_idx, condition) in record.wait_conditions.iter().enumerate() {
+                let prefix = match (cond_idx, &condition.compare_type) {
+                    (0, _) => "",
+                    (_, CompareType::And) => "and ",
+                    (_, CompareType::Or) => "or ",
+                };
+
+                lines.push(Line {
+                    text: format!("{prefix}{}", wait_condition_text(condition)),
+                    color: CONDITION_COLOR,
+                    indent: size * 2,
+                });
+            }
+        }
+    }
+
+    lines
+}
+
+/// Appends a sidebar listing the train schedules of the blueprint to the right of the render.
+///
+/// Returns the render unchanged if there are no schedules.
+#[must_use]
+pub fn attach(img: DynamicImage, schedules: &[Schedule], font: &Font) -> DynamicImage {
+    if schedules.is_empty() {
+        return img;
+    }
+
+    let size = (img.height() / 40).clamp(14, 32);
+    let line_height = size + size / 4;
+    let lines = lines(schedules, size);
+
+    let max_width = (f64::from(img.width()) * MAX_WIDTH_FACTOR).max(f64::from(size * 16)) as u32;
+    let text_width = lines
+        .iter()
+        .map(|line| line.indent + font.width(&line.text, size.into()))
+        .max()
+        .unwrap_or(0)
+        .min(max_width);
+
+    let Ok(line_count) = u32::try_from(lines.len()) else {
+        return img;
+    };
+
+    let sidebar_width = text_width + PADDING * 2;
+    let sidebar_height = line_count * line_height + PADDING * 2;
+
+    let mut res = RgbaImage::new(
+        img.width() + sidebar_width,
+        img.height().max(sidebar_height),
+    );
+    imageops::overlay(&mut res, &img, 0, 0);
+
+    let sidebar = RgbaImage::from_pixel(sidebar_width, res.height(), BACKGROUND);
+    imageops::overlay(&mut res, &sidebar, img.width().into(), 0);
+
+    let mut y = PADDING;
+    for line in &lines {
+        let text = font.fit(
+            &line.text,
+            size.into(),
+            text_width.saturating_sub(line.indent),
+        );
+        if let Some(text) = font.render(&text, size.into(), line.color) {
+            let x = img.width() + PADDING + line.indent;
+            imageops::overlay(&mut res, &text, x.into(), y.into());
+        }
+
+        y += line_height;
+    }
+
+    res.into()
+}
@@ -769,16 +769,19 @@ impl RenderableGraphics for RotatedSpriteParams {
         image_cache: &mut ImageCache,
         opts: &Self::RenderOpts,
     ) -> Option<GraphicsOutput> {
-        let mut index = direction_count_to_index(
-            self.direction_count,
-            opts.orientation,
-            self.back_equals_front,
-        );
+        let orientation = if self.apply_projection {
+            opts.orientation.projected_orientation()
+        } else {
+            opts.orientation
+        };
+
+        let mut index =
+            direction_count_to_index(self.direction_count, orientation, self.back_equals_front);
         if self.counterclockwise {
-            index = self.direction_count - index - 1;
+            index = (self.direction_count - index) % self.direction_count;
         }
 
-        // TODO: support `axially_symmetrical` and `apply_projection` (and `allow_low_quality_rotation`?)
+        // TODO: support `axially_symmetrical` (and `allow_low_quality_rotation`?)
 
         let line_length = if self.line_length == 0 {
             self.direction_count
@@ -864,16 +867,19 @@ impl RenderableGraphics for RotatedSpriteParamsMultiFile {
             return None;
         }
 
-        let mut index = direction_count_to_index(
-            self.direction_count,
-            opts.orientation,
-            self.back_equals_front,
-        );
+        let orientation = if self.apply_projection {
+            opts.orientation.projected_orientation()
+        } else {
+            opts.orientation
+        };
+
+        let mut index =
+            direction_count_to_index(self.direction_count, orientation, self.back_equals_front);
         if self.counterclockwise {
-            index = self.direction_count - index - 1;
+            index = (self.direction_count - index) % self.direction_count;
         }
 
-        // TODO: support `axially_symmetrical` and `apply_projection` (and `allow_low_quality_rotation`?)
+        // TODO: support `axially_symmetrical` (and `allow_low_quality_rotation`?)
 
         let line_length = if self.line_length == 0 {
             self.direction_count
@@ -1261,6 +1267,59 @@ impl Scale for SpriteSheetParams {
     }
 }
 
+impl SpriteSheet {
+    /// Renders the selected variation of the sheet, out of range variations wrap around.
+    fn render_variation(
+        &self,
+        scale: f64,
+        used_mods: &UsedMods,
+        image_cache: &mut ImageCache,
+        opts: &SpriteVariationsRenderOpts,
+    ) -> Option<GraphicsOutput> {
+        match self {
+            Self::Layered { layers } => {
+                let layers = layers
+                    .iter()
+                    .map(|layer| layer.render_variation(scale, used_mods, image_cache, opts))
+                    .collect::<Vec<_>>();
+
+                merge_renders(&layers, scale)
+            }
+            Self::Simple {
+                filename,
+                data,
+                hr_version,
+            } => {
+                // TODO: option to enable/disable HR mode
+                if let Some(hr_version) = hr_version {
+                    if scale < data.scale() {
+                        return hr_version.render_variation(scale, used_mods, image_cache, opts);
+                    }
+                }
+
+                let variation = opts.variation % data.variation_count.max(1);
+                let line_length = data
+                    .line_length
+                    .filter(|l| *l > 0)
+                    .unwrap_or(data.variation_count)
+                    .max(1);
+
+                data.fetch_offset(
+                    scale,
+                    filename,
+                    used_mods,
+                    image_cache,
+                    opts.runtime_tint,
+                    (
+                        (variation % line_length) as i16,
+                        (variation / line_length) as i16,
+                    ),
+                )
+            }
+        }
+    }
+}
+
 /// [`Types/SpriteSheet`](https://lua-api.factorio.com/latest/types/SpriteSheet.html)
 pub type SpriteSheet = SimpleGraphics<SpriteSheetParams>;
 
@@ -1299,13 +1358,12 @@ impl RenderableGraphics for SpriteVariations {
     ) -> Option<GraphicsOutput> {
         match self {
             Self::Struct { sheet } | Self::SpriteSheet(sheet) => {
-                sheet.render(scale, used_mods, image_cache, &opts.into())
-            }
-            Self::Array(variations) => {
-                variations
-                    .get(0)?
-                    .render(scale, used_mods, image_cache, &opts.into())
+                sheet.render_variation(scale, used_mods, image_cache, opts)
             }
+            Self::Array(variations) => variations
+                .get(opts.variation as usize)
+                .or_else(|| variations.first())?
+                .render(scale, used_mods, image_cache, &opts.into()),
         }
     }
 }
@@ -1366,6 +1424,49 @@ impl<T: FetchSprite> RenderableGraphics for TileGraphics<T> {
     }
 }
 
+/// Crops a rectangle (in pixels) of a tile sprite sheet, scales it to the target scale and applies the tint.
+#[allow(clippy::too_many_arguments)]
+fn fetch_tile_rect(
+    scale: f64,
+    sprite_scale: f64,
+    filename: &FileName,
+    used_mods: &UsedMods,
+    image_cache: &mut ImageCache,
+    runtime_tint: Option<Color>,
+    (x, y): (SpriteSizeType, SpriteSizeType),
+    (width, height): (SpriteSizeType, SpriteSizeType),
+) -> Option<GraphicsOutput> {
+    let img = filename.load(used_mods, image_cache)?.crop_imm(
+        x as u32,
+        y as u32,
+        width as u32,
+        height as u32,
+    );
+
+    let mut img = img.resize(
+        (f64::from(img.width()) * sprite_scale / scale).round() as u32,
+        (f64::from(img.height()) * sprite_scale / scale).round() as u32,
+        image::imageops::FilterType::Nearest,
+    );
+
+    if let Some(tint) = runtime_tint {
+        if !Color::is_white(&tint) {
+            let mut img_buf = img.to_rgba8();
+            let [tint_r, tint_g, tint_b, tint_a] = tint.to_rgba();
+
+            for Rgba([r, g, b, a]) in img_buf.pixels_mut() {
+                *r = (f64::from(*r) * tint_r).round() as u8;
+                *g = (f64::from(*g) * tint_g).round() as u8;
+                *b = (f64::from(*b) * tint_b).round() as u8;
+                *a = (f64::from(*a) * tint_a).round() as u8;
+            }
+            img = img_buf.into();
+        }
+    }
+
+    Some((img, Vector::default()))
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TileSpriteParams {
     #[serde(
@@ -1450,37 +1551,17 @@ impl FetchSprite for TileSpriteParams {
     ) -> Option<GraphicsOutput> {
         let (x, y) = self.get_position();
         let (offset_x, offset_y) = offset;
-        let (width, height) = self.get_size();
-
-        let img = filename.load(used_mods, image_cache)?.crop_imm(
-            (x + offset_x) as u32,
-            (y + offset_y) as u32,
-            width as u32,
-            height as u32,
-        );
 
-        let mut img = img.resize(
-            (f64::from(img.width()) * self.scale / scale).round() as u32,
-            (f64::from(img.height()) * self.scale / scale).round() as u32,
-            image::imageops::FilterType::Nearest,
-        );
-
-        if let Some(tint) = runtime_tint {
-            if !Color::is_white(&tint) {
-                let mut img_buf = img.to_rgba8();
-                let [tint_r, tint_g, tint_b, tint_a] = tint.to_rgba();
-
-                for Rgba([r, g, b, a]) in img_buf.pixels_mut() {
-                    *r = (f64::from(*r) * tint_r).round() as u8;
-                    *g = (f64::from(*g) * tint_g).round() as u8;
-                    *b = (f64::from(*b) * tint_b).round() as u8;
-                    *a = (f64::from(*a) * tint_a).round() as u8;
-                }
-                img = img_buf.into();
-            }
-        }
-
-        Some((img, Vector::default()))
+        fetch_tile_rect(
+            scale,
+            self.scale(),
+            filename,
+            used_mods,
+            image_cache,
+            runtime_tint,
+            (x + offset_x, y + offset_y),
+            self.get_size(),
+        )
     }
 
     fn get_position(&self) -> (i16, i16) {
@@ -1499,9 +1580,92 @@ impl Scale for TileSpriteParams {
     }
 }
 
+impl TileSpriteParams {
+    /// Size of a single tile on the sprite sheet in pixels.
+    fn tile_size(&self) -> SpriteSizeType {
+        (32.0 / self.scale).round() as SpriteSizeType
+    }
+
+    /// Number of variations in a single row of the sprite sheet.
+    const fn variations_per_line(&self) -> u32 {
+        if self.line_length == 0 {
+            if self.count == 0 {
+                1
+            } else {
+                self.count
+            }
+        } else {
+            self.line_length
+        }
+    }
+
+    /// Fetches a rectangle of the sprite sheet, `offset` and `size` are given in tiles.
+    #[allow(clippy::too_many_arguments)]
+    fn fetch_tiles(
+        &self,
+        scale: f64,
+        filename: &FileName,
+        used_mods: &UsedMods,
+        image_cache: &mut ImageCache,
+        runtime_tint: Option<Color>,
+        (column, row): (u32, u32),
+        (width, height): (u32, u32),
+    ) -> Option<GraphicsOutput> {
+        let tile_size = self.tile_size();
+        let to_pixels = |tiles: u32| SpriteSizeType::try_from(tiles).ok()?.checked_mul(tile_size);
+
+        fetch_tile_rect(
+            scale,
+            self.scale,
+            filename,
+            used_mods,
+            image_cache,
+            runtime_tint,
+            (
+                self.x.checked_add(to_pixels(column)?)?,
+                self.y.checked_add(to_pixels(row)?)?,
+            ),
+            (to_pixels(width)?, to_pixels(height)?),
+        )
+    }
+}
+
 /// [`Types/TileSprite`](https://lua-api.factorio.com/latest/types/TileSprite.html)
 pub type TileSprite = TileGraphics<TileSpriteParams>;
 
+impl TileSprite {
+    /// Renders a single variation (`variation % count`) of the sheet.
+    #[must_use]
+    pub fn render_variation(
+        &self,
+        scale: f64,
+        used_mods: &UsedMods,
+        image_cache: &mut ImageCache,
+        opts: &SimpleGraphicsRenderOpts,
+        variation: u32,
+    ) -> Option<GraphicsOutput> {
+        // TODO: option to enable/disable HR mode
+        if let Some(hr_version) = &self.hr_version {
+            if scale < self.scale() {
+                return hr_version.render_variation(scale, used_mods, image_cache, opts, variation);
+            }
+        }
+
+        let variation = variation % self.count.max(1);
+        let line_length = self.variations_per_line();
+
+        self.fetch_tiles(
+            scale,
+            &self.picture,
+            used_mods,
+            image_cache,
+            opts.runtime_tint,
+            (variation % line_length, variation / line_length),
+            (1, 1),
+        )
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TileSpriteProbabilityParams {
     #[serde(deserialize_with = "helper::truncating_deserializer")]
@@ -1567,37 +1731,17 @@ impl FetchSprite for TileSpriteProbabilityParams {
     ) -> Option<GraphicsOutput> {
         let (x, y) = self.get_position();
         let (offset_x, offset_y) = offset;
-        let (width, height) = self.get_size();
 
-        let img = filename.load(used_mods, image_cache)?.crop_imm(
-            (x + offset_x) as u32,
-            (y + offset_y) as u32,
-            width as u32,
-            height as u32,
-        );
-
-        let mut img = img.resize(
-            (f64::from(img.width()) * self.scale / scale).round() as u32,
-            (f64::from(img.height()) * self.scale / scale).round() as u32,
-            image::imageops::FilterType::Nearest,
-        );
-
-        if let Some(tint) = runtime_tint {
-            if !Color::is_white(&tint) {
-                let mut img_buf = img.to_rgba8();
-                let [tint_r, tint_g, tint_b, tint_a] = tint.to_rgba();
-
-                for Rgba([r, g, b, a]) in img_buf.pixels_mut() {
-                    *r = (f64::from(*r) * tint_r).round() as u8;
-                    *g = (f64::from(*g) * tint_g).round() as u8;
-                    *b = (f64::from(*b) * tint_b).round() as u8;
-                    *a = (f64::from(*a) * tint_a).round() as u8;
-                }
-                img = img_buf.into();
-            }
-        }
-
-        Some((img, Vector::default()))
+        fetch_tile_rect(
+            scale,
+            self.scale(),
+            filename,
+            used_mods,
+            image_cache,
+            runtime_tint,
+            (x + offset_x, y + offset_y),
+            self.get_size(),
+        )
     }
 
     fn get_position(&self) -> (i16, i16) {
@@ -1628,6 +1772,53 @@ impl std::ops::Deref for TileSpriteProbabilityParams {
 /// [`Types/TileSpriteWithProbability`](https://lua-api.factorio.com/latest/types/TileSpriteWithProbability.html)
 pub type TileSpriteWithProbability = TileGraphics<TileSpriteProbabilityParams>;
 
+impl TileSpriteWithProbability {
+    /// Renders a single tile of a variation (`variation % count`).
+    ///
+    /// Every variation covers `size` x `size` tiles, `piece` selects the tile inside of it.
+    #[must_use]
+    pub fn render_variation(
+        &self,
+        scale: f64,
+        used_mods: &UsedMods,
+        image_cache: &mut ImageCache,
+        opts: &SimpleGraphicsRenderOpts,
+        variation: u32,
+        (piece_x, piece_y): (u32, u32),
+    ) -> Option<GraphicsOutput> {
+        // TODO: option to enable/disable HR mode
+        if let Some(hr_version) = &self.hr_version {
+            if scale < self.scale() {
+                return hr_version.render_variation(
+                    scale,
+                    used_mods,
+                    image_cache,
+                    opts,
+                    variation,
+                    (piece_x, piece_y),
+                );
+            }
+        }
+
+        let size = self.size.max(1);
+        let variation = variation % self.count.max(1);
+        let line_length = self.variations_per_line();
+
+        self.tile_sprite_params.fetch_tiles(
+            scale,
+            &self.picture,
+            used_mods,
+            image_cache,
+            opts.runtime_tint,
+            (
+                (variation % line_length) * size + piece_x % size,
+                (variation / line_length) * size + piece_y % size,
+            ),
+            (1, 1),
+        )
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TileTransitionSpriteParams {
     #[serde(default, skip_serializing_if = "std::ops::Not::not")]
@@ -1645,9 +1836,142 @@ impl std::ops::Deref for TileTransitionSpriteParams {
     }
 }
 
+impl FetchSprite for TileTransitionSpriteParams {
+    fn fetch(
+        &self,
+        scale: f64,
+        filename: &FileName,
+        used_mods: &UsedMods,
+        image_cache: &mut ImageCache,
+        runtime_tint: Option<Color>,
+    ) -> Option<GraphicsOutput> {
+        self.fetch_offset_by_pixels(
+            scale,
+            filename,
+            used_mods,
+            image_cache,
+            runtime_tint,
+            (0, 0),
+        )
+    }
+
+    fn fetch_offset(
+        &self,
+        scale: f64,
+        filename: &FileName,
+        used_mods: &UsedMods,
+        image_cache: &mut ImageCache,
+        runtime_tint: Option<Color>,
+        offset: (i16, i16),
+    ) -> Option<GraphicsOutput> {
+        let (width, height) = self.get_size();
+        self.fetch_offset_by_pixels(
+            scale,
+            filename,
+            used_mods,
+            image_cache,
+            runtime_tint,
+            (offset.0 * width, offset.1 * height),
+        )
+    }
+
+    fn fetch_offset_by_pixels(
+        &self,
+        scale: f64,
+        filename: &FileName,
+        used_mods: &UsedMods,
+        image_cache: &mut ImageCache,
+        runtime_tint: Option<Color>,
+        offset: (i16, i16),
+    ) -> Option<GraphicsOutput> {
+        let (x, y) = self.get_position();
+        let (offset_x, offset_y) = offset;
+
+        fetch_tile_rect(
+            scale,
+            self.scale(),
+            filename,
+            used_mods,
+            image_cache,
+            runtime_tint,
+            (x + offset_x, y + offset_y),
+            self.get_size(),
+        )
+    }
+
+    fn get_position(&self) -> (i16, i16) {
+        self.tile_sprite_params.get_position()
+    }
+
+    fn get_size(&self) -> (i16, i16) {
+        let (width, height) = self.tile_sprite_params.get_size();
+        if self.tall {
+            (width, height * 2)
+        } else {
+            (width, height)
+        }
+    }
+}
+
+impl Scale for TileTransitionSpriteParams {
+    fn scale(&self) -> f64 {
+        self.tile_sprite_params.scale()
+    }
+}
+
 /// [`Types/TileTransitionSprite`](https://lua-api.factorio.com/latest/types/TileTransitionSprite.html)
 pub type TileTransitionSprite = TileGraphics<TileTransitionSpriteParams>;
 
+impl TileTransitionSprite {
+    /// Renders a single variation (`variation % count`) of the transition in the given direction.
+    ///
+    /// The variations are laid out horizontally, every row of the sheet is one direction.
+    /// Tall transitions are 2 tiles high and shifted so that their top tile covers the target tile.
+    #[must_use]
+    pub fn render_transition(
+        &self,
+        scale: f64,
+        used_mods: &UsedMods,
+        image_cache: &mut ImageCache,
+        opts: &SimpleGraphicsRenderOpts,
+        variation: u32,
+        direction: u32,
+    ) -> Option<GraphicsOutput> {
+        // TODO: option to enable/disable HR mode
+        if let Some(hr_version) = &self.hr_version {
+            if scale < self.scale() {
+                return hr_version.render_transition(
+                    scale,
+                    used_mods,
+                    image_cache,
+                    opts,
+                    variation,
+                    direction,
+                );
+            }
+        }
+
+        let height = if self.tall { 2 } else { 1 };
+        let (img, _) = self.tile_sprite_params.fetch_tiles(
+            scale,
+            &self.picture,
+            used_mods,
+            image_cache,
+            opts.runtime_tint,
+            (variation % self.count.max(1), direction * height),
+            (1, height),
+        )?;
+
+        let shift = if self.tall {
+            Vector::new(0.0, 0.5)
+        } else {
+            Vector::default()
+        };
+
+        Some((img, shift))
+    }
+}
+
 // ======================== //
 // =====[ Animations ]===== //
 // ======================== //
@@ -2370,11 +2694,17 @@ impl FetchSprite for RotatedAnimationParams {
 impl RotatedAnimationParams {
     #[must_use]
     pub fn orientation_index(&self, orientation: RealOrientation) -> u32 {
+        let orientation = if self.apply_projection {
+            orientation.projected_orientation()
+        } else {
+            orientation
+        };
+
         let index =
             (orientation * f64::from(self.direction_count)).round() as u32 % self.direction_count;
 
         if self.counterclockwise {
-            self.direction_count - index - 1
+            (self.direction_count - index) % self.direction_count
         } else {
             index
         }
@@ -2474,7 +2804,8 @@ impl RenderableGraphics for RotatedAnimation {
                 let orientation_index = opts
                     .override_index
                     .map_or_else(|| data.orientation_index(opts.orientation), u32::from);
-                let file_index = orientation_index / data.lines_per_file.unwrap_or(1);
+                let lines_per_file = data.lines_per_file.unwrap_or(1).max(1);
+                let file_index = orientation_index / lines_per_file;
                 let frame_index = data.animation_params.frame_index(opts.progress);
                 let line_length = data.animation_params.line_length();
 
@@ -2487,7 +2818,10 @@ impl RenderableGraphics for RotatedAnimation {
                     used_mods,
                     image_cache,
                     opts.runtime_tint,
-                    (column as i16, (row + orientation_index) as i16),
+                    (
+                        column as i16,
+                        (row + orientation_index % lines_per_file) as i16,
+                    ),
                 )
             }
             Self::Single {
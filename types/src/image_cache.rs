@@ -0,0 +1,318 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, MutexGuard, PoisonError,
+    },
+};
+
+use sha1::{Digest, Sha1};
+
+const DISK_MAGIC: &[u8; 4] = b"FSIC";
+
+/// Makes the names of temporary files unique when multiple threads store the same image.
+static TMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug)]
+struct CacheEntry {
+    image: Option<Arc<image::DynamicImage>>,
+    last_used: u64,
+    size: usize,
+}
+
+#[derive(Debug, Default)]
+struct CacheData {
+    entries: HashMap<String, CacheEntry>,
+    lru: BTreeMap<u64, String>,
+    tick: u64,
+    size: usize,
+
+    max_size: Option<usize>,
+    disk_dir: Option<PathBuf>,
+}
+
+impl CacheData {
+    /// Marks the entry as most recently used.
+    fn touch(&mut self, key: &str) -> Option<&CacheEntry> {
+        self.tick += 1;
+        let entry = self.entries.get_mut(key)?;
+        self.lru.remove(&entry.last_used);
+        self.lru.insert(self.tick, key.to_owned());
+        entry.last_used = self.tick;
+
+        Some(entry)
+    }
+
+    fn insert(&mut self, key: String, image: Option<Arc<image::DynamicImage>>) {
+        let size = image.as_ref().map_or(0, |img| img.as_bytes().len());
+
+        if let Some(old) = self.entries.remove(&key) {
+            self.lru.remove(&old.last_used);
+            self.size -= old.size;
+        }
+
+        if let Some(max_size) = self.max_size {
+            while self.size + size > max_size {
+                let Some((_, evicted)) = self.lru.pop_first() else {
+                    break;
+                };
+
+                if let Some(entry) = self.entries.remove(&evicted) {
+                    self.size -= entry.size;
+                }
+            }
+        }
+
+        self.tick += 1;
+        self.lru.insert(self.tick, key.clone());
+        self.size += size;
+        self.entries.insert(
+            key,
+            CacheEntry {
+                image,
+                last_used: self.tick,
+                size,
+            },
+        );
+    }
+}
+
+/// Cache for decoded sprite images.
+///
+/// Images are keyed by mod name + mod version + file path so that a long lived cache
+/// stays valid when different mod sets are used. The in-memory cache can be bounded by
+/// the size of the decoded images, the least recently used ones get evicted first.
+/// Optionally the decoded images are also stored on disk to skip the zip extraction
+/// and PNG decoding on the next start.
+///
+/// Clones share the same underlying cache so it can be used from multiple threads.
+#[derive(Debug, Clone, Default)]
+pub struct ImageCache {
+    data: Arc<Mutex<CacheData>>,
+}
+
+impl ImageCache {
+    /// Unbounded in-memory cache without disk caching.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, CacheData> {
+        // the cache is always left in a consistent state, so a poisoned lock can be reused
+        self.data.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Limits the in-memory cache to `max_size` bytes of decoded image data.
+    #[must_use]
+    pub fn with_max_size(self, max_size: usize) -> Self {
+        self.lock().max_size = Some(max_size);
+        self
+    }
+
+    /// Stores decoded images in `dir` and loads them from there if available.
+    #[must_use]
+    pub fn with_disk_cache(self, dir: PathBuf) -> Self {
+        if let Err(err) = fs::create_dir_all(&dir) {
+            log::warn!(
+                "error creating image cache directory {}: {err}",
+                dir.display()
+            );
+            return self;
+        }
+
+        self.lock().disk_dir = Some(dir);
+        self
+    }
+
+    /// Number of cached images (including failed loads).
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.lock().entries.is_empty()
+    }
+
+    /// Size of all decoded images in the in-memory cache in bytes.
+    #[must_use]
+    pub fn size(&self) -> usize {
+        self.lock().size
+    }
+
+    pub fn clear(&mut self) {
+        let mut data = self.lock();
+        data.entries.clear();
+        data.lru.clear();
+        data.size = 0;
+    }
+
+    /// Returns the cached image for `key` or loads it with `load` (or from the disk cache).
+    ///
+    /// Failed loads are cached as well so they are not retried on every access.
+    /// The lock is not held while loading, so other threads can use the cache in the meantime.
+    pub fn get_or_load(
+        &mut self,
+        key: &str,
+        load: impl FnOnce() -> Option<image::DynamicImage>,
+    ) -> Option<Arc<image::DynamicImage>> {
+        let disk_dir = {
+            let mut data = self.lock();
+            if let Some(entry) = data.touch(key) {
+                return entry.image.clone();
+            }
+
+            data.disk_dir.clone()
+        };
+
+        let image = disk_dir
+            .as_deref()
+            .and_then(|dir| load_from_disk(dir, key))
+            .or_else(|| {
+                let image = load()?;
+                if let Some(dir) = &disk_dir {
+                    store_on_disk(dir, key, &image);
+                }
+
+                Some(image)
+            })
+            .map(Arc::new);
+
+        self.lock().insert(key.to_owned(), image.clone());
+        image
+    }
+}
+
+/// The file name has to stay the same across builds, so a stable hash is used instead of `DefaultHasher`.
+fn disk_path(dir: &Path, key: &str) -> PathBuf {
+    dir.join(format!("{:x}.img", Sha1::digest(key.as_bytes())))
+}
+
+fn load_from_disk(dir: &Path, key: &str) -> Option<image::DynamicImage> {
+    let mut file = fs::File::open(disk_path(dir, key)).ok()?;
+
+    let read_u32 = |file: &mut fs::File| {
+        let mut buf = [0; 4];
+        file.read_exact(&mut buf).ok()?;
+        Some(u32::from_le_bytes(buf))
+    };
+
+    let mut magic = [0; 4];
+    file.read_exact(&mut magic).ok()?;
+    if &magic != DISK_MAGIC {
+        return None;
+    }
+
+    // the key is stored as well to detect hash collisions
+    let key_len = read_u32(&mut file)? as usize;
+    let mut stored_key = vec![0; key_len];
+    file.read_exact(&mut stored_key).ok()?;
+    if stored_key != key.as_bytes() {
+        return None;
+    }
+
+    let width = read_u32(&mut file)?;
+    let height = read_u32(&mut file)?;
+
+    let mut data = Vec::new();
+    file.read_to_end(&mut data).ok()?;
+
+    image::RgbaImage::from_raw(width, height, data).map(image::DynamicImage::ImageRgba8)
+}
+
+fn store_on_disk(dir: &Path, key: &str, image: &image::DynamicImage) {
+    let path = disk_path(dir, key);
+    let rgba = image.to_rgba8();
+    let Ok(key_len) = u32::try_from(key.len()) else {
+        return;
+    };
+
+    // write to a temporary file first so that concurrent readers never see partial files
+    let tmp_path = path.with_extension(format!(
+        "{}-{}.tmp",
+        std::process::id(),
+        TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let res = fs::File::create(&tmp_path).and_then(|mut file| {
+        file.write_all(DISK_MAGIC)?;
+        file.write_all(&key_len.to_le_bytes())?;
+        file.write_all(key.as_bytes())?;
+        file.write_all(&rgba.width().to_le_bytes())?;
+        file.write_all(&rgba.height().to_le_bytes())?;
+        file.write_all(rgba.as_raw())?;
+        fs::rename(&tmp_path, &path)
+    });
+
+    if let Err(err) = res {
+        log::warn!("error storing {key} in image cache: {err}");
+        fs::remove_file(&tmp_path).ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(size: u32) -> image::DynamicImage {
+        image::DynamicImage::ImageRgba8(image::RgbaImage::new(size, size))
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        // every image is 4x4 rgba = 64 bytes
+        let mut cache = ImageCache::new().with_max_size(128);
+
+        assert!(cache.get_or_load("a", || Some(image(4))).is_some());
+        assert!(cache.get_or_load("b", || Some(image(4))).is_some());
+        assert_eq!(cache.size(), 128);
+
+        // touch a so that b is the least recently used one
+        assert!(cache.get_or_load("a", || None).is_some());
+        assert!(cache.get_or_load("c", || Some(image(4))).is_some());
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.size(), 128);
+        assert!(cache.get_or_load("a", || None).is_some());
+        assert!(cache.get_or_load("b", || None).is_none());
+    }
+
+    #[test]
+    fn clones_share_entries() {
+        let mut cache = ImageCache::new();
+        let mut other = cache.clone();
+
+        assert!(cache.get_or_load("a", || Some(image(1))).is_some());
+        assert!(other.get_or_load("a", || None).is_some());
+        assert_eq!(other.len(), 1);
+    }
+
+    #[test]
+    fn caches_failed_loads() {
+        let mut cache = ImageCache::new();
+
+        assert!(cache.get_or_load("missing", || None).is_none());
+        assert!(cache.get_or_load("missing", || Some(image(1))).is_none());
+        assert_eq!(cache.size(), 0);
+    }
+
+    #[test]
+    fn disk_cache_round_trip() {
+        let dir = std::env::temp_dir().join(format!("image_cache_test_{}", std::process::id()));
+
+        let mut cache = ImageCache::new().with_disk_cache(dir.clone());
+        assert!(cache.get_or_load("disk", || Some(image(2))).is_some());
+
+        let mut cache = ImageCache::new().with_disk_cache(dir.clone());
+        let loaded = cache
+            .get_or_load("disk", || None)
+            .map(|img| (img.width(), img.height()));
+        assert_eq!(loaded, Some((2, 2)));
+
+        fs::remove_dir_all(dir).ok();
+    }
+}
@@ -5,7 +5,7 @@
     clippy::module_name_repetitions
 )]
 
-use std::{collections::HashMap, fmt, hash::Hash};
+use std::{fmt, hash::Hash};
 
 use konst::{primitive::parse_u16, result::unwrap_ctx};
 
@@ -29,6 +29,7 @@ mod empty_array_fix;
 mod energy;
 mod graphics;
 mod icon;
+mod image_cache;
 mod item;
 mod module;
 mod wire;
@@ -37,6 +38,7 @@ pub use empty_array_fix::*;
 pub use energy::*;
 pub use graphics::*;
 pub use icon::*;
+pub use image_cache::*;
 pub use item::*;
 pub use module::*;
 pub use wire::*;
@@ -634,25 +636,19 @@ pub enum Vector3D {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileName(String);
 
-pub type ImageCache = HashMap<String, Option<image::DynamicImage>>;
-
 impl FileName {
     #[must_use]
     pub const fn new(filename: String) -> Self {
         Self(filename)
     }
 
-    pub fn load<'a>(
+    pub fn load(
         &self,
         used_mods: &UsedMods,
-        image_cache: &'a mut ImageCache,
-    ) -> Option<&'a image::DynamicImage> {
+        image_cache: &mut ImageCache,
+    ) -> Option<std::sync::Arc<image::DynamicImage>> {
         let filename = &self.0;
 
-        if image_cache.contains_key(filename) {
-            return image_cache.get(filename)?.as_ref();
-        }
-
         let re = regex::Regex::new(r"^__([^/\\]+)__").ok()?;
         let mod_name = re.captures(filename)?.get(1)?.as_str();
         let sprite_path = &filename[(2 + mod_name.len() + 2 + 1)..]; // +1 to include the slash to prevent joining to interpret it as a absolute path
@@ -662,22 +658,20 @@ impl FileName {
             return None;
         };
 
-        let file_data = match m.get_file(sprite_path) {
-            Ok(d) => d,
-            Err(e) => {
-                println!("Error loading {filename}: {e}");
-                return None;
-            }
-        };
+        // the version is part of the key so that a shared cache can be used with different mod sets
+        let key = format!("__{mod_name}__@{}/{sprite_path}", m.info.version);
 
-        let img = image::load_from_memory_with_format(
-            &used_mods.get(mod_name)?.get_file(sprite_path).ok()?,
-            image::ImageFormat::Png,
-        )
-        .ok();
+        image_cache.get_or_load(&key, || {
+            let file_data = match m.get_file(sprite_path) {
+                Ok(d) => d,
+                Err(e) => {
+                    println!("Error loading {filename}: {e}");
+                    return None;
+                }
+            };
 
-        image_cache.insert(filename.clone(), img);
-        image_cache.get(filename)?.as_ref()
+            image::load_from_memory_with_format(&file_data, image::ImageFormat::Png).ok()
+        })
     }
 }
 
@@ -1017,7 +1011,7 @@ pub type VirtualSignalID = String;
 /// [`Types/FluidID`](https://lua-api.factorio.com/latest/types/FluidID.html)
 pub type FluidID = String;
 
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub enum PipeConnectionType {
     #[default]
@@ -1150,35 +1144,59 @@ pub struct FluidBox {
 impl FluidBox {
     #[must_use]
     pub fn connection_points(&self, direction: Direction) -> Vec<MapPosition> {
+        self.connections(direction)
+            .into_iter()
+            .map(|(position, _)| position)
+            .collect()
+    }
+
+    /// Connection points together with the direction the fluid can flow through them.
+    #[must_use]
+    pub fn connections(&self, direction: Direction) -> Vec<(MapPosition, PipeConnectionType)> {
         self.pipe_connections
             .iter()
             .filter_map(|c| match c {
                 PipeConnectionDefinition::Directional {
                     positions,
                     max_underground_distance,
-                    ..
+                    type_,
                 } => {
                     if *max_underground_distance != 0 {
                         return None;
                     }
 
                     let cardinal = direction as u8 / 2;
-                    positions.get(cardinal as usize).map(|v| (*v).into())
+                    positions
+                        .get(cardinal as usize)
+                        .map(|v| ((*v).into(), *type_))
                 }
                 PipeConnectionDefinition::Static {
                     position,
                     max_underground_distance,
-                    ..
+                    type_,
                 } => {
                     if *max_underground_distance != 0 {
                         return None;
                     }
 
-                    Some(direction.rotate_vector(*position).into())
+                    Some((direction.rotate_vector(*position).into(), *type_))
                 }
             })
             .collect()
     }
+
+    /// Connections that are shown with arrows in alt-mode.
+    #[must_use]
+    pub fn indicated_connections(
+        &self,
+        direction: Direction,
+    ) -> Vec<(MapPosition, PipeConnectionType)> {
+        if self.hide_connection_info {
+            return Vec::new();
+        }
+
+        self.connections(direction)
+    }
 }
 
 /// [`Types/RecipeID`](https://lua-api.factorio.com/latest/types/RecipeID.html)
@@ -2004,9 +2022,12 @@ pub struct BeaconGraphicsSet {
     pub module_tint_mode: ModuleTintMode,
 }
 
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Default)]
 pub struct BeaconGraphicsSetRenderOpts {
     pub runtime_tint: Option<Color>,
+
+    /// Content of each module slot, `None` for empty slots.
+    pub modules: Vec<Option<InsertedModule>>,
 }
 
 impl From<&BeaconGraphicsSetRenderOpts> for AnimationRenderOpts {
@@ -2028,14 +2049,64 @@ impl RenderableGraphics for BeaconGraphicsSet {
         image_cache: &mut ImageCache,
         opts: &Self::RenderOpts,
     ) -> Option<GraphicsOutput> {
-        // TODO: render module visualisations
-        merge_layers(
+        let mut renders = vec![merge_layers(
             &self.animation_list,
             scale,
             used_mods,
             image_cache,
             &opts.into(),
-        )
+        )];
+
+        let mut visualisations = Vec::new();
+        for (slot, module) in opts.modules.iter().enumerate() {
+            let Some(set) = self.module_visualisations.iter().find(|v| {
+                module.as_ref().map_or(v.use_for_empty_slots, |module| {
+                    module.art_style.as_ref() == Some(&v.art_style)
+                })
+            }) else {
+                continue;
+            };
+
+            let Some(layers) = set.slots.get(slot) else {
+                continue;
+            };
+
+            for layer in layers {
+                // lights are not rendered & the module tints are not known
+                if !layer.draw_as_sprite || layer.apply_module_tint != ModuleTint::None {
+                    continue;
+                }
+
+                // the first variation is the empty slot if the visualisation has one
+                let variation = match module {
+                    Some(module) => {
+                        i64::from(module.tier) + i64::from(set.tier_offset)
+                            - i64::from(!layer.has_empty_slot)
+                    }
+                    None if layer.has_empty_slot => 0,
+                    None => continue,
+                };
+
+                visualisations.push((layer.secondary_draw_order, layer, variation.max(0)));
+            }
+        }
+
+        visualisations.sort_by_key(|(order, _, _)| *order);
+        renders.extend(visualisations.into_iter().map(|(_, layer, variation)| {
+            layer.pictures.as_ref().and_then(|p| {
+                p.render(
+                    scale,
+                    used_mods,
+                    image_cache,
+                    &SpriteVariationsRenderOpts {
+                        variation: u32::try_from(variation).unwrap_or_default(),
+                        runtime_tint: opts.runtime_tint,
+                    },
+                )
+            })
+        }));
+
+        merge_renders(&renders, scale)
     }
 }
 
@@ -2967,6 +3038,21 @@ pub enum Comparator {
     NotEqual,
 }
 
+impl fmt::Display for Comparator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            Self::Less => "<",
+            Self::LessOrEqual => "≤",
+            Self::Greater => ">",
+            Self::GreaterOrEqual => "≥",
+            Self::Equal => "=",
+            Self::NotEqual => "≠",
+        };
+
+        f.write_str(symbol)
+    }
+}
+
 // https://lua-api.factorio.com/latest/concepts.html#ArithmeticCombinatorParameters
 #[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
 pub enum ArithmeticOperation {
@@ -3003,3 +3089,23 @@ pub enum ArithmeticOperation {
     #[serde(rename = "XOR")]
     BitwiseXor,
 }
+
+impl fmt::Display for ArithmeticOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            Self::Multiply => "*",
+            Self::Divide => "/",
+            Self::Add => "+",
+            Self::Subtract => "-",
+            Self::Modulo => "%",
+            Self::Power => "^",
+            Self::LeftShift => "<<",
+            Self::RightShift => ">>",
+            Self::BitwiseAnd => "AND",
+            Self::BitwiseOr => "OR",
+            Self::BitwiseXor => "XOR",
+        };
+
+        f.write_str(symbol)
+    }
+}
@@ -34,6 +34,13 @@ pub struct ModuleSpecification {
     pub module_info_multi_row_initial_height_modifier: Option<f32>,
 }
 
+/// A module inserted into an entity, as far as it matters for the beacon module visualisations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InsertedModule {
+    pub art_style: Option<String>,
+    pub tier: u32,
+}
+
 /// [`Types/ModuleCategoryID`](https://lua-api.factorio.com/latest/types/ModuleCategoryID.html)
 pub type ModuleCategoryID = String;
 